@@ -3,6 +3,9 @@ use quote::{quote, ToTokens};
 use std::{fs::File, io::Write};
 use syn::{spanned::Spanned, Error, FnArg, ImplItem, ImplItemMethod, ItemImpl, ReturnType};
 
+/// Implements `FfiHandler` for the methods of an `impl` block, and generates the FFI for the guest, see the documentation of `embedded_wasm::derive_ffi_handler`.
+///
+/// Arguments and results can only be `i32`, `i64`, `f32` or `f64`. `ExternRef` is rejected, as the generated FFI can not declare an `externref`.
 #[proc_macro]
 pub fn derive_ffi_handler(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let result = derive_ffi_handler_inner(stream.into())
@@ -100,7 +103,9 @@ fn export_ffi(functions: &[Function], out: &str) -> std::io::Result<()> {
         write!(&mut file, ")")?;
 
         if let ReturnType::Type(_, ty) = &function.item.sig.output {
-            write!(&mut file, " -> {}", ty.to_token_stream())?;
+            if !is_unit(ty) {
+                write!(&mut file, " -> {}", ty.to_token_stream())?;
+            }
         }
         writeln!(&mut file, ";")?;
     }
@@ -125,9 +130,14 @@ impl Function {
         let mut error = None;
         let name = &self.item.sig.ident;
         let output = match &self.item.sig.output {
-            ReturnType::Type(_, ty) => Some(ty),
-            ReturnType::Default => None,
+            // `-> ()` pushes no result, like a function without a return type
+            ReturnType::Type(_, ty) if !is_unit(ty) => Some(ty),
+            _ => None,
         };
+        if let Some(ty) = output {
+            validate_ty_name(&ty.into_token_stream().to_string())
+                .map_err(|e| Error::new(ty.span(), e))?;
+        }
         let mut stream = TokenStream::new();
         stream.extend([
             lit_str(name, name.span()),
@@ -145,6 +155,7 @@ impl Function {
                         error = Some(Error::new(ty.span(), e));
                         break;
                     }
+                    let getter = format!("as_{}", ty_name);
                     _stream.extend([
                         ident("let"),
                         ident(&name),
@@ -158,14 +169,13 @@ impl Function {
                         punct('.'),
                         ident("map"),
                         group(Delimiter::Parenthesis, |stream| {
-                            let name = format!("as_{}", &ty_name);
                             stream.extend([
                                 punct('|'),
                                 ident("a"),
                                 punct('|'),
                                 ident("a"),
                                 punct('.'),
-                                ident_spanned(&name, ty.span()),
+                                ident_spanned(&getter, ty.span()),
                                 group(Delimiter::Parenthesis, |_| {}),
                             ])
                         }),
@@ -290,9 +300,17 @@ fn group(delimiter: Delimiter, group: impl FnOnce(&mut TokenStream)) -> TokenTre
     Group::new(delimiter, stream).into()
 }
 
+fn is_unit(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Tuple(tuple) if tuple.elems.is_empty())
+}
+
 fn validate_ty_name(name: &str) -> Result<(), String> {
     match name {
         "i32" | "i64" | "f32" | "f64" => Ok(()),
+        // Rust has no type for `externref`, so it can not be declared in the FFI for the guest
+        "ExternRef" | "Option < ExternRef >" => Err(String::from(
+            "ExternRef is not supported, because it can not be declared in the generated FFI. Implement `FfiHandler` manually to pass extern references",
+        )),
         x => Err(format!(
             "Invalid type {:?}, only i32, i64, f32 or f64 supported",
             x
@@ -0,0 +1,176 @@
+use crate::{
+    instruction::{Instruction, ValType},
+    ParseResult, Reader, Vec,
+};
+use alloc::borrow::Cow;
+
+/// The body of a function, with the targets of its branches resolved when the module is validated.
+///
+/// The instructions are kept in their binary encoding, and are decoded while they are executed. Only the control instructions have an entry in the side table of [`Jump`]s, so a body takes a few bytes per instruction.
+#[derive(Debug)]
+pub struct Body<'a> {
+    pub locals: Vec<(u32, ValType)>,
+    /// The encoded instructions of the function, including the final `end`.
+    ///
+    /// With the `flash` feature these are borrowed from the module, otherwise they are copied into RAM.
+    pub code: Cow<'a, [u8]>,
+    /// The resolved branches of the control instructions, sorted by the offset of the instruction.
+    pub jumps: Vec<Jump>,
+}
+
+impl<'a> Body<'a> {
+    /// Decode the instruction at `offset`. Returns the instruction and the offset of the next instruction.
+    ///
+    /// The body is validated, so this only fails if `offset` is not the start of an instruction.
+    pub fn instruction(&self, offset: usize) -> ParseResult<'_, (Instruction, usize)> {
+        let mut reader = Reader::new(self.code.get(offset..).unwrap_or_default());
+        let instruction = Instruction::parse(&mut reader)?;
+        Ok((instruction, self.code.len() - reader.remaining().len()))
+    }
+
+    /// The jumps of the control instruction at `offset`.
+    pub fn jumps(&self, offset: usize) -> &[Jump] {
+        let offset = offset as u32;
+        let start = self.jumps.partition_point(|jump| jump.offset < offset);
+        let len = self.jumps[start..].partition_point(|jump| jump.offset == offset);
+        &self.jumps[start..][..len]
+    }
+
+    /// The jump of the `if`, `else`, `br` or `br_if` at `offset`.
+    pub fn jump(&self, offset: usize) -> &Branch {
+        &self.jumps(offset)[0].branch
+    }
+}
+
+/// A reference to the body of a function that a process is executing.
+///
+/// With the `lazy` feature a process decodes the bodies itself, and shares them between its frames and its cache of recently called functions.
+#[cfg(not(feature = "lazy"))]
+pub type BodyRef<'a> = &'a Body<'a>;
+#[cfg(feature = "lazy")]
+pub type BodyRef<'a> = alloc::rc::Rc<Body<'a>>;
+
+/// The resolved branch of a control instruction.
+///
+/// An `if` jumps to the start of its `else` branch or past its `end` when the condition is false, and an `else` jumps past the `end` of its `if`.
+/// A `br_table` has a jump for every label, the last jump is the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jump {
+    /// The offset of the control instruction in the body.
+    pub offset: u32,
+    pub branch: Branch,
+}
+
+/// The resolved label of a branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Branch {
+    /// The offset of the instruction to continue at.
+    pub target: u32,
+    /// The height of the stack at the label, relative to the height of the stack when the function was called.
+    pub height: u32,
+    /// The amount of values that the branch carries to the label.
+    pub arity: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instruction::FuncIdx, Wasm};
+    use core::mem::size_of;
+
+    #[test]
+    fn instruction_out_of_bounds() {
+        // An `end`, and an `i32.const` that ends in the middle of its immediate
+        let body = Body {
+            locals: Vec::new(),
+            code: Cow::Borrowed(&[0x0B, 0x41, 0x80]),
+            jumps: Vec::new(),
+        };
+        assert_eq!(body.instruction(0).unwrap(), (Instruction::End, 1));
+        assert!(body.instruction(1).is_err());
+        assert!(body.instruction(3).is_err());
+        assert!(body.instruction(4).is_err());
+    }
+
+    /// Measures the memory that grows with the amount of instructions: the encoded instructions and their jumps.
+    ///
+    /// The `Body` itself, its locals and the overhead of its allocations take a fixed amount of memory per function, and are not included.
+    #[test]
+    fn code_and_jump_bytes_per_instruction() {
+        // (module
+        //   (import "env" "set_led" (func $set_led (param i32)))
+        //   (import "env" "sleep" (func $sleep (param i32)))
+        //   (memory 1)
+        //   (global $state (mut i32) (i32.const 0))
+        //   (func $toggle (result i32)
+        //     (global.set $state (i32.xor (global.get $state) (i32.const 1)))
+        //     (global.get $state))
+        //   (func $delay (param $step i32) (result i32)
+        //     (block $slow
+        //       (block $medium
+        //         (block $fast
+        //           (br_table $fast $medium $slow (local.get $step)))
+        //         (return (i32.const 100)))
+        //       (return (i32.const 250)))
+        //     (i32.const 500))
+        //   (func (export "main") (local $i i32)
+        //     (loop $forever
+        //       (call $set_led (call $toggle))
+        //       (local.set $i (i32.const 0))
+        //       (block $break
+        //         (loop $count
+        //           (br_if $break (i32.ge_u (local.get $i) (i32.const 10)))
+        //           (i32.store (i32.shl (local.get $i) (i32.const 2)) (local.get $i))
+        //           (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        //           (br $count)))
+        //       (if (i32.load (i32.const 4))
+        //         (then (call $sleep (call $delay (i32.const 1))))
+        //         (else (call $sleep (call $delay (i32.const 2)))))
+        //       (br $forever))))
+        const BLINK: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x11, 0x04, 0x60, 0x01, 0x7f,
+            0x00, 0x60, 0x00, 0x01, 0x7f, 0x60, 0x01, 0x7f, 0x01, 0x7f, 0x60, 0x00, 0x00, 0x02,
+            0x1b, 0x02, 0x03, 0x65, 0x6e, 0x76, 0x07, 0x73, 0x65, 0x74, 0x5f, 0x6c, 0x65, 0x64,
+            0x00, 0x00, 0x03, 0x65, 0x6e, 0x76, 0x05, 0x73, 0x6c, 0x65, 0x65, 0x70, 0x00, 0x00,
+            0x03, 0x04, 0x03, 0x01, 0x02, 0x03, 0x05, 0x03, 0x01, 0x00, 0x01, 0x06, 0x06, 0x01,
+            0x7f, 0x01, 0x41, 0x00, 0x0b, 0x07, 0x08, 0x01, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00,
+            0x04, 0x0a, 0x72, 0x03, 0x0b, 0x00, 0x23, 0x00, 0x41, 0x01, 0x73, 0x24, 0x00, 0x23,
+            0x00, 0x0b, 0x1d, 0x00, 0x02, 0x40, 0x02, 0x40, 0x02, 0x40, 0x20, 0x00, 0x0e, 0x02,
+            0x00, 0x01, 0x02, 0x0b, 0x41, 0xe4, 0x00, 0x0f, 0x0b, 0x41, 0xfa, 0x01, 0x0f, 0x0b,
+            0x41, 0xf4, 0x03, 0x0b, 0x46, 0x01, 0x01, 0x7f, 0x03, 0x40, 0x10, 0x02, 0x10, 0x00,
+            0x41, 0x00, 0x21, 0x00, 0x02, 0x40, 0x03, 0x40, 0x20, 0x00, 0x41, 0x0a, 0x4f, 0x0d,
+            0x01, 0x20, 0x00, 0x41, 0x02, 0x74, 0x20, 0x00, 0x36, 0x02, 0x00, 0x20, 0x00, 0x41,
+            0x01, 0x6a, 0x21, 0x00, 0x0c, 0x00, 0x0b, 0x0b, 0x41, 0x04, 0x28, 0x02, 0x00, 0x04,
+            0x40, 0x41, 0x01, 0x10, 0x03, 0x10, 0x01, 0x05, 0x41, 0x02, 0x10, 0x03, 0x10, 0x01,
+            0x0b, 0x0c, 0x00, 0x0b, 0x0b,
+        ];
+        let wasm = Wasm::parse(BLINK).unwrap();
+
+        let mut instructions = 0;
+        let mut bytes = 0;
+        for function in 2..5 {
+            #[cfg(not(feature = "lazy"))]
+            let body = wasm.get_code(FuncIdx(function)).unwrap();
+            #[cfg(feature = "lazy")]
+            let body = &wasm.decode_code(FuncIdx(function)).unwrap().unwrap();
+            let mut offset = 0;
+            while offset < body.code.len() {
+                offset = body.instruction(offset).unwrap().1;
+                instructions += 1;
+            }
+            if let Cow::Owned(code) = &body.code {
+                bytes += code.capacity();
+            }
+            bytes += body.jumps.capacity() * size_of::<Jump>();
+        }
+        assert_eq!(instructions, 57);
+        assert!(
+            bytes <= instructions * 5,
+            "{} bytes of code and jumps for {} instructions",
+            bytes,
+            instructions
+        );
+        // The fixed part of every function is the headers of its three vectors
+        assert_eq!(size_of::<Body>(), 9 * size_of::<usize>());
+    }
+}
@@ -0,0 +1,325 @@
+//! Floating point operations with wasm semantics.
+//!
+//! Most of the rounding functions (`floor`, `sqrt`, etc) of `f32` and `f64` are only available in `std`, so they are implemented here with integer operations. This also makes sure they behave the same on targets without an FPU.
+
+use crate::Trap;
+
+pub trait Float: Sized {
+    fn wasm_abs(self) -> Self;
+    fn wasm_neg(self) -> Self;
+    fn wasm_copysign(self, sign: Self) -> Self;
+    fn wasm_trunc(self) -> Self;
+    fn wasm_floor(self) -> Self;
+    fn wasm_ceil(self) -> Self;
+    /// Round to the nearest integer, rounding ties to even.
+    fn wasm_nearest(self) -> Self;
+    /// Correctly rounded square root.
+    fn wasm_sqrt(self) -> Self;
+    fn wasm_min(self, other: Self) -> Self;
+    fn wasm_max(self, other: Self) -> Self;
+
+    fn trunc_i32(self) -> Result<i32, Trap>;
+    fn trunc_u32(self) -> Result<u32, Trap>;
+    fn trunc_i64(self) -> Result<i64, Trap>;
+    fn trunc_u64(self) -> Result<u64, Trap>;
+}
+
+macro_rules! impl_float {
+    ($($ty:ty => { bits: $bits:ty, wide: $wide:ty, mantissa: $mantissa:expr, exponent_bias: $bias:expr }),*) => {
+        $(
+            impl Float for $ty {
+                fn wasm_abs(self) -> Self {
+                    <$ty>::from_bits(self.to_bits() & !SIGN)
+                }
+
+                fn wasm_neg(self) -> Self {
+                    <$ty>::from_bits(self.to_bits() ^ SIGN)
+                }
+
+                fn wasm_copysign(self, sign: Self) -> Self {
+                    <$ty>::from_bits((self.to_bits() & !SIGN) | (sign.to_bits() & SIGN))
+                }
+
+                fn wasm_trunc(self) -> Self {
+                    let bits = self.to_bits();
+                    let exponent = ((bits & !SIGN) >> $mantissa) as i32 - $bias;
+                    if exponent >= $mantissa {
+                        // Already an integer, infinite or NaN
+                        quiet(self)
+                    } else if exponent < 0 {
+                        <$ty>::from_bits(bits & SIGN)
+                    } else {
+                        let fraction: $bits = ((1 as $bits) << $mantissa) - 1;
+                        <$ty>::from_bits(bits & !(fraction >> exponent))
+                    }
+                }
+
+                fn wasm_floor(self) -> Self {
+                    let truncated = self.wasm_trunc();
+                    if self < 0.0 && truncated != self {
+                        truncated - 1.0
+                    } else {
+                        truncated
+                    }
+                }
+
+                fn wasm_ceil(self) -> Self {
+                    let truncated = self.wasm_trunc();
+                    if self > 0.0 && truncated != self {
+                        truncated + 1.0
+                    } else {
+                        truncated
+                    }
+                }
+
+                fn wasm_nearest(self) -> Self {
+                    // Every float with a magnitude of at least `2^mantissa` is already an integer
+                    const LIMIT: $ty = ((1 as $bits) << $mantissa) as $ty;
+                    let abs = self.wasm_abs();
+                    if self.is_nan() {
+                        quiet(self)
+                    } else if abs >= LIMIT {
+                        self
+                    } else {
+                        // Adding `LIMIT` pushes all fraction bits out of the mantissa, which rounds ties to even.
+                        ((abs + LIMIT) - LIMIT).wasm_copysign(self)
+                    }
+                }
+
+                fn wasm_sqrt(self) -> Self {
+                    let bits = self.to_bits();
+                    if self.is_nan() {
+                        return quiet(self);
+                    }
+                    if self == 0.0 || self == <$ty>::INFINITY {
+                        // also matches -0.0
+                        return self;
+                    }
+                    if bits & SIGN != 0 {
+                        return <$ty>::NAN;
+                    }
+
+                    // Split into `mantissa * 2^exponent`, normalizing subnormals.
+                    let mut exponent = (bits >> $mantissa) as i32;
+                    let mut mantissa = bits & (((1 as $bits) << $mantissa) - 1);
+                    if exponent == 0 {
+                        exponent = 1;
+                        while mantissa & ((1 as $bits) << $mantissa) == 0 {
+                            mantissa <<= 1;
+                            exponent -= 1;
+                        }
+                    } else {
+                        mantissa |= (1 as $bits) << $mantissa;
+                    }
+                    exponent -= $bias + $mantissa;
+                    if (exponent - $mantissa - 2) & 1 != 0 {
+                        mantissa <<= 1;
+                        exponent -= 1;
+                    }
+
+                    // `mantissa` is now in `[2^m, 2^(m+2))`, so the root of this value has exactly `m + 2` bits.
+                    // The lowest bit is used for rounding.
+                    let value = (mantissa as $wide) << ($mantissa + 2);
+                    let mut root: $wide = 0;
+                    let mut remainder = value;
+                    let mut bit: $wide = 1 << (<$wide>::BITS - 2);
+                    while bit > value {
+                        bit >>= 2;
+                    }
+                    while bit != 0 {
+                        if remainder >= root + bit {
+                            remainder -= root + bit;
+                            root = (root >> 1) + bit;
+                        } else {
+                            root >>= 1;
+                        }
+                        bit >>= 2;
+                    }
+
+                    let guard = root & 1 != 0;
+                    let mut result = (root >> 1) as $bits;
+                    if guard && (remainder != 0 || result & 1 != 0) {
+                        result += 1;
+                    }
+                    let mut exponent = (exponent - $mantissa - 2) / 2 + 1 + $mantissa;
+                    if result == (1 as $bits) << ($mantissa + 1) {
+                        result >>= 1;
+                        exponent += 1;
+                    }
+                    let biased = (exponent + $bias) as $bits;
+                    <$ty>::from_bits((biased << $mantissa) | (result & (((1 as $bits) << $mantissa) - 1)))
+                }
+
+                fn wasm_min(self, other: Self) -> Self {
+                    if self.is_nan() || other.is_nan() {
+                        <$ty>::NAN
+                    } else if self == 0.0 && other == 0.0 {
+                        // -0.0 is smaller than 0.0
+                        <$ty>::from_bits(self.to_bits() | other.to_bits())
+                    } else if self < other {
+                        self
+                    } else {
+                        other
+                    }
+                }
+
+                fn wasm_max(self, other: Self) -> Self {
+                    if self.is_nan() || other.is_nan() {
+                        <$ty>::NAN
+                    } else if self == 0.0 && other == 0.0 {
+                        // 0.0 is larger than -0.0
+                        <$ty>::from_bits(self.to_bits() & other.to_bits())
+                    } else if self > other {
+                        self
+                    } else {
+                        other
+                    }
+                }
+
+                fn trunc_i32(self) -> Result<i32, Trap> {
+                    trunc_checked(self, -2147483648.0, 2147483648.0).map(|f| f as i32)
+                }
+
+                fn trunc_u32(self) -> Result<u32, Trap> {
+                    trunc_checked(self, 0.0, 4294967296.0).map(|f| f as u32)
+                }
+
+                fn trunc_i64(self) -> Result<i64, Trap> {
+                    trunc_checked(self, -9223372036854775808.0, 9223372036854775808.0).map(|f| f as i64)
+                }
+
+                fn trunc_u64(self) -> Result<u64, Trap> {
+                    trunc_checked(self, 0.0, 18446744073709551616.0).map(|f| f as u64)
+                }
+            }
+        )*
+    };
+}
+
+mod f32_impl {
+    use super::*;
+
+    const SIGN: u32 = 1 << 31;
+    const QUIET: u32 = 1 << 22;
+
+    fn quiet(f: f32) -> f32 {
+        if f.is_nan() {
+            f32::from_bits(f.to_bits() | QUIET)
+        } else {
+            f
+        }
+    }
+
+    /// Truncate `f`, trapping if the result is not in the range `min..max`.
+    fn trunc_checked(f: f32, min: f32, max: f32) -> Result<f32, Trap> {
+        if f.is_nan() {
+            return Err(Trap::InvalidConversionToInteger);
+        }
+        let truncated = f.wasm_trunc();
+        if truncated >= min && truncated < max {
+            Ok(truncated)
+        } else {
+            Err(Trap::IntegerOverflow)
+        }
+    }
+
+    impl_float!(f32 => { bits: u32, wide: u64, mantissa: 23, exponent_bias: 127 });
+}
+
+mod f64_impl {
+    use super::*;
+
+    const SIGN: u64 = 1 << 63;
+    const QUIET: u64 = 1 << 51;
+
+    fn quiet(f: f64) -> f64 {
+        if f.is_nan() {
+            f64::from_bits(f.to_bits() | QUIET)
+        } else {
+            f
+        }
+    }
+
+    /// Truncate `f`, trapping if the result is not in the range `min..max`.
+    fn trunc_checked(f: f64, min: f64, max: f64) -> Result<f64, Trap> {
+        if f.is_nan() {
+            return Err(Trap::InvalidConversionToInteger);
+        }
+        let truncated = f.wasm_trunc();
+        if truncated >= min && truncated < max {
+            Ok(truncated)
+        } else {
+            Err(Trap::IntegerOverflow)
+        }
+    }
+
+    impl_float!(f64 => { bits: u64, wide: u128, mantissa: 52, exponent_bias: 1023 });
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::Float;
+
+    #[test]
+    fn matches_std() {
+        let mut values = std::vec![
+            0.0,
+            -0.0,
+            0.5,
+            -0.5,
+            1.5,
+            2.5,
+            -2.5,
+            3.5,
+            0.49999997,
+            1e-40,
+            2.0,
+            4.0,
+            1e30,
+            -7.25,
+            8388607.5,
+            f64::MIN_POSITIVE,
+            5e-324,
+            1.7976931348623157e308,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        let mut seed = 0x2545F4914F6CDD1Du64;
+        for _ in 0..10_000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            values.push(f64::from_bits(seed));
+            values.push(f32::from_bits(seed as u32) as f64);
+        }
+        for value in values {
+            let single = value as f32;
+            let same = |a: f64, b: f64| a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan());
+            assert!(same(value.wasm_sqrt(), value.sqrt()), "sqrt({:e})", value);
+            assert!(
+                same(single.wasm_sqrt() as f64, single.sqrt() as f64),
+                "sqrt({:e})",
+                single
+            );
+            if value.is_nan() {
+                continue;
+            }
+            assert_eq!(
+                value.wasm_nearest().to_bits(),
+                value.round_ties_even().to_bits()
+            );
+            assert_eq!(value.wasm_floor().to_bits(), value.floor().to_bits());
+            assert_eq!(value.wasm_ceil().to_bits(), value.ceil().to_bits());
+            assert_eq!(value.wasm_trunc().to_bits(), value.trunc().to_bits());
+            assert_eq!(
+                single.wasm_nearest().to_bits(),
+                single.round_ties_even().to_bits()
+            );
+            assert_eq!(single.wasm_floor().to_bits(), single.floor().to_bits());
+            assert_eq!(single.wasm_ceil().to_bits(), single.ceil().to_bits());
+            assert_eq!(single.wasm_trunc().to_bits(), single.trunc().to_bits());
+        }
+    }
+}
@@ -1,10 +1,9 @@
-mod execute;
 mod parse;
 mod types;
 
-pub use self::{execute::*, parse::*, types::*};
+pub use self::{parse::*, types::*};
 
-use crate::Vec;
+use alloc::boxed::Box;
 
 // TODO: Organize these based on the order of chapter 2.4
 // TODO: Copy documentation from chapter 2.4 to the relevant instructions
@@ -13,32 +12,29 @@ pub enum Instruction {
     // 5.4.1 Control instructions
     Unreachable,
     Nop,
+    /// The start of a `block`. The instructions of the block follow, up to the matching [`Instruction::End`].
     Block {
         bt: BlockType,
-        inner: Vec<Instruction>,
     },
+    /// The start of a `loop`. The instructions of the loop follow, up to the matching [`Instruction::End`].
     Loop {
         bt: BlockType,
-        inner: Vec<Instruction>,
     },
+    /// The start of an `if`. The instructions of the `if` branch follow, up to the matching [`Instruction::Else`] or [`Instruction::End`].
     If {
         bt: BlockType,
-        inner: Vec<Instruction>,
-    },
-    IfElse {
-        bt: BlockType,
-        if_inner: Vec<Instruction>,
-        else_inner: Vec<Instruction>,
     },
+    Else,
+    End,
     Branch {
         index: LabelIdx,
     },
     BranchIf {
         index: LabelIdx,
     },
+    /// The labels are boxed, as they are rare and would otherwise make every instruction larger.
     BranchTable {
-        labels: Vec<LabelIdx>,
-        index: LabelIdx,
+        table: Box<LabelTable>,
     },
     Return,
     Call {
@@ -62,7 +58,7 @@ pub enum Instruction {
     Drop,
     Select,
     SelectVal {
-        val: Vec<ValType>,
+        val: ValType,
     },
 
     // 5.4.4 Variable instructions
@@ -296,6 +292,15 @@ pub enum Instruction {
     I64Extend16Signed,
     I64Extend32Signed,
 
+    I32TruncSatF32Signed,
+    I32TruncSatF32Unsigned,
+    I32TruncSatF64Signed,
+    I32TruncSatF64Unsigned,
+    I64TruncSatF32Signed,
+    I64TruncSatF32Unsigned,
+    I64TruncSatF64Signed,
+    I64TruncSatF64Unsigned,
+
     Vector(VectorInstruction),
 }
 
@@ -427,7 +432,7 @@ pub enum VectorInstruction {
     I16x8Mul,
     I16x8Min(Signedness),
     I16x8Max(Signedness),
-    I16x8Average(Signedness),
+    I16x8Average,
     I16x8ExtMulLowI8x16(Signedness),
     I16x8ExtMulHighI8x16(Signedness),
 
@@ -509,6 +514,6 @@ pub struct LaneIdx(pub u8);
 
 #[test]
 fn instruction_size() {
-    assert_eq!(core::mem::size_of::<Instruction>(), 72);
+    assert_eq!(core::mem::size_of::<Instruction>(), 24);
     assert_eq!(core::mem::size_of::<LaneIdx>(), 1);
 }
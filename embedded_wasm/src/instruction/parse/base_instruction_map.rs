@@ -1,8 +1,9 @@
 use super::{MemArg, NumType, ValType};
 use crate::{
-    instruction::{BlockType, Instruction, RefType, Signedness},
+    instruction::{BlockType, Instruction, LabelTable, RefType, Signedness},
     ErrorKind, Mark, ParseResult, Reader,
 };
+use alloc::boxed::Box;
 
 fn unknown_instruction<'a>(_: &mut Reader<'a>, mark: Mark<'a>) -> ParseResult<'a, Instruction> {
     Err(mark.into_error(ErrorKind::UnknownInstruction))
@@ -21,43 +22,30 @@ pub const INSTRUCTIONS: [for<'a> fn(
     |reader, _mark| {
         Ok(Instruction::Block {
             bt: BlockType::parse(reader)?,
-            inner: Instruction::parse_until_0x0B(reader)?,
         })
     },
     // 0x03
     |reader, _mark| {
         Ok(Instruction::Loop {
             bt: BlockType::parse(reader)?,
-            inner: Instruction::parse_until_0x0B(reader)?,
         })
     },
     // 0x04
     |reader, _mark| {
-        Ok({
-            let bt = BlockType::parse(reader)?;
-            let (if_inner, else_inner) = Instruction::parse_until_0x0B_with_else(reader)?;
-            if let Some(else_inner) = else_inner {
-                Instruction::IfElse {
-                    bt,
-                    if_inner,
-                    else_inner,
-                }
-            } else {
-                Instruction::If {
-                    bt,
-                    inner: if_inner,
-                }
-            }
+        Ok(Instruction::If {
+            bt: BlockType::parse(reader)?,
         })
     },
-    // 0x05 .. 0x0B
-    unknown_instruction,
-    unknown_instruction,
+    // 0x05
+    |_, _| Ok(Instruction::Else),
+    // 0x06 .. 0x0A
     unknown_instruction,
     unknown_instruction,
     unknown_instruction,
     unknown_instruction,
     unknown_instruction,
+    // 0x0B
+    |_, _| Ok(Instruction::End),
     // 0x0C
     |reader, _mark| {
         Ok(Instruction::Branch {
@@ -74,8 +62,10 @@ pub const INSTRUCTIONS: [for<'a> fn(
     |reader, _mark| {
         Ok({
             let labels = reader.read_vec(|r| r.read_index())?;
-            let index = reader.read_index()?;
-            Instruction::BranchTable { labels, index }
+            let default = reader.read_index()?;
+            Instruction::BranchTable {
+                table: Box::new(LabelTable { labels, default }),
+            }
         })
     },
     // 0x0F
@@ -109,8 +99,13 @@ pub const INSTRUCTIONS: [for<'a> fn(
     |_, _| Ok(Instruction::Select),
     // 0x1C
     |reader, _mark| {
+        // The binary format allows any amount of types, but only a single type is valid
+        let mark = reader.mark();
+        if reader.read_int::<u32>()? != 1 {
+            return Err(mark.into_error(ErrorKind::InvalidSelectType));
+        }
         Ok(Instruction::SelectVal {
-            val: reader.read_vec(ValType::parse)?,
+            val: ValType::parse(reader)?,
         })
     },
     // 0x1D .. 0x1F
@@ -1,7 +1,7 @@
 mod base_instruction_map;
 mod vector_instruction_map;
 
-use super::{BlockType, Instruction, MemArg, NumType, RefType, SectionType, ValType};
+use super::{BlockType, Instruction, MemArg, NumType, RefType, SectionType, TypeIdx, ValType};
 use crate::{instruction::LaneIdx, ErrorKind, Mark, ParseResult, Reader, Vec};
 
 impl Instruction {
@@ -14,46 +14,6 @@ impl Instruction {
         Ok(instructions)
     }
 
-    #[allow(non_snake_case)]
-    fn parse_until_0x0B_with_else<'a>(
-        reader: &mut Reader<'a>,
-    ) -> ParseResult<'a, (Vec<Instruction>, Option<Vec<Instruction>>)> {
-        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-        enum IfOrElse {
-            If,
-            Else,
-        }
-        let mut if_inner = Vec::new();
-        let mut else_inner = Vec::new();
-        let mut step = IfOrElse::If;
-        while !reader.read_u8_if(|b| b == 0x0B)? {
-            if reader.read_u8_if(|b| b == 0x05)? {
-                if step == IfOrElse::Else {
-                    return Err(reader
-                        .mark_relative(-1)
-                        .into_error(ErrorKind::DuplicateElse));
-                }
-                step = IfOrElse::Else;
-            } else {
-                if step == IfOrElse::If {
-                    &mut if_inner
-                } else {
-                    &mut else_inner
-                }
-                .push(Instruction::parse(reader)?);
-            }
-        }
-
-        Ok((
-            if_inner,
-            if step == IfOrElse::If {
-                None
-            } else {
-                Some(else_inner)
-            },
-        ))
-    }
-
     pub fn parse<'a>(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
         let mark = reader.mark();
         let idx = reader.read_u8()? as usize;
@@ -63,14 +23,14 @@ impl Instruction {
     fn parse_extended<'a>(reader: &mut Reader<'a>, _mark: Mark<'a>) -> ParseResult<'a, Self> {
         let mark = reader.mark();
         Ok(match reader.read_int::<u32>()? {
-            0 => unimplemented!("i32.trunc_<sat>_f32_signed, next: {:?}", reader.read_u8()),
-            1 => unimplemented!("i32.trunc_<sat>_f32_unsigned, next: {:?}", reader.read_u8()),
-            2 => unimplemented!("i32.trunc_<sat>_f64_signed, next: {:?}", reader.read_u8()),
-            3 => unimplemented!("i32.trunc_<sat>_f64_unsigned, next: {:?}", reader.read_u8()),
-            4 => unimplemented!("i64.trunc_<sat>_f32_signed, next: {:?}", reader.read_u8()),
-            5 => unimplemented!("i64.trunc_<sat>_f32_unsigned, next: {:?}", reader.read_u8()),
-            6 => unimplemented!("i64.trunc_<sat>_f64_signed, next: {:?}", reader.read_u8()),
-            7 => unimplemented!("i64.trunc_<sat>_f64_unsigned, next: {:?}", reader.read_u8()),
+            0 => Self::I32TruncSatF32Signed,
+            1 => Self::I32TruncSatF32Unsigned,
+            2 => Self::I32TruncSatF64Signed,
+            3 => Self::I32TruncSatF64Unsigned,
+            4 => Self::I64TruncSatF32Signed,
+            5 => Self::I64TruncSatF32Unsigned,
+            6 => Self::I64TruncSatF64Signed,
+            7 => Self::I64TruncSatF64Unsigned,
             8 => {
                 let index = reader.read_index()?;
                 let _nul = reader.read_u8()?;
@@ -122,23 +82,26 @@ impl Instruction {
                 .map(Instruction::Vector)
         }
     }
-
-    pub fn parse_vec<'a>(reader: &mut Reader<'a>) -> ParseResult<'a, Vec<Self>> {
-        let mut result = Vec::with_capacity(reader.remaining().len());
-        while !reader.is_empty() {
-            result.push(Self::parse(reader)?);
-        }
-        result.shrink_to_fit();
-        Ok(result)
-    }
 }
 
 impl BlockType {
     pub fn parse<'a>(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
-        Ok(match reader.read_u8()? {
-            0x40 => Self::Empty,
-            x => panic!("Unknown blocktype 0x{:02X}", x),
-        })
+        let byte = reader.peek_u8()?;
+        if byte == 0x40 {
+            reader.read_u8()?;
+            return Ok(Self::Empty);
+        }
+        if let Ok(val_type) = ValType::from_u8(byte) {
+            reader.read_u8()?;
+            return Ok(Self::ValType(val_type));
+        }
+        // Type indices are encoded as a positive signed 33 bit integer
+        let mark = reader.mark();
+        let idx: i64 = reader.read_int()?;
+        if !(0..=u32::MAX as i64).contains(&idx) {
+            return Err(mark.into_error(ErrorKind::InvalidBlockType));
+        }
+        Ok(Self::Type(TypeIdx(idx as u32)))
     }
 }
 
@@ -162,6 +125,7 @@ impl ValType {
             0x7E => Self::Num(NumType::I64),
             0x7D => Self::Num(NumType::F32),
             0x7C => Self::Num(NumType::F64),
+            0x7B => Self::V128,
             0x70 => Self::Ref(RefType::FuncRef),
             0x6F => Self::Ref(RefType::ExternRef),
             _ => return Err(ErrorKind::UnknownValType),
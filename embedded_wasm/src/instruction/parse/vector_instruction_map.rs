@@ -53,8 +53,9 @@ pub const INSTRUCTIONS: [for<'a> fn(
         let mut lanes = [LaneIdx::default(); 16];
         for (idx, lane) in lanes.iter_mut().enumerate() {
             *lane = LaneIdx(slice[idx]);
-            if lane.0 >= 16 {
-                return Err(mark.into_error(ErrorKind::InvalidLaneIndex { max: 16 }));
+            // Lanes index into the concatenation of both vectors
+            if lane.0 >= 32 {
+                return Err(mark.into_error(ErrorKind::InvalidLaneIndex { max: 32 }));
             }
         }
         Ok(I8x16Shuffle(lanes))
@@ -276,7 +277,7 @@ pub const INSTRUCTIONS: [for<'a> fn(
     // 101
     |_, _| Ok(I8x16NarrowI16x8(Signed)),
     // 102
-    |_, _| Ok(I8x16NarrowI16x8(Signed)),
+    |_, _| Ok(I8x16NarrowI16x8(Unsigned)),
     // 103
     |_, _| Ok(F32x4Ceil),
     // 104
@@ -382,7 +383,7 @@ pub const INSTRUCTIONS: [for<'a> fn(
     // 154
     unknown_instruction,
     // 155
-    |_, _| Ok(I16x8Average(Unsigned)),
+    |_, _| Ok(I16x8Average),
     // 156
     |_, _| Ok(I16x8ExtMulLowI8x16(Signed)),
     // 157
@@ -547,14 +548,14 @@ pub const INSTRUCTIONS: [for<'a> fn(
     |_, _| Ok(F64x2Abs),
     // 237
     |_, _| Ok(F64x2Neg),
-    // 239
+    // 238
     unknown_instruction,
     // 239
     |_, _| Ok(F64x2Sqrt),
     // 240
     |_, _| Ok(F64x2Add),
     // 241
-    |_, _| Ok(F64x2Add),
+    |_, _| Ok(F64x2Sub),
     // 242
     |_, _| Ok(F64x2Mul),
     // 243
@@ -1,9 +1,19 @@
-#[derive(Clone, Debug, PartialEq)]
+use crate::Vec;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct MemArg {
     pub align: u32,
     pub offset: u32,
 }
 
+/// The labels of a `br_table` instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabelTable {
+    pub labels: Vec<LabelIdx>,
+    /// The label that is used when the operand is out of range of `labels`.
+    pub default: LabelIdx,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum BlockType {
     Empty,
@@ -24,7 +34,7 @@ pub trait IndexAlias {
 macro_rules! impl_idx {
     ($name:ident (prefix: $prefix:expr)) => {
         #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-        pub struct $name(pub usize);
+        pub struct $name(pub u32);
 
         impl core::fmt::Debug for $name {
             fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -34,7 +44,7 @@ macro_rules! impl_idx {
 
         impl IndexAlias for $name {
             fn new(val: u32) -> Self {
-                Self(val as usize)
+                Self(val)
             }
         }
     };
@@ -50,7 +60,7 @@ impl_idx!(LocalIdx (prefix: "$l"));
 impl_idx!(ElemIdx (prefix: "$e"));
 impl_idx!(DataIdx (prefix: "$d"));
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SectionType {
     Custom = 0,
     Type = 1,
@@ -84,5 +94,7 @@ pub enum RefType {
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ValType {
     Num(NumType),
+    /// The 128-bit vector type of the SIMD instructions.
+    V128,
     Ref(RefType),
 }
@@ -3,17 +3,23 @@
 #![no_std]
 extern crate alloc;
 
+mod bytecode;
 mod ffi;
+mod float;
 mod instruction;
 mod process;
 mod reader;
 mod section;
+#[cfg(test)]
+mod test_utils;
+mod typed_func;
 mod utils;
 mod wasm;
 
 pub use self::{
     ffi::FfiHandler,
-    process::{Dynamic, Process, ProcessAction},
+    process::{Dynamic, ExternRef, Process, ProcessAction, Value},
+    typed_func::{TypedFunc, WasmParams, WasmResults, WasmType},
     wasm::Wasm,
 };
 
@@ -113,6 +119,10 @@ pub use self::{
 /// - `i64`
 /// - `f32`
 /// - `f64`
+///
+/// [`ExternRef`] is rejected, because Rust has no type for `externref` that can be used in the generated FFI. To pass extern references to a module, implement [`FfiHandler`] manually.
+///
+/// A method without a return type, or one that returns `()`, pushes no result.
 pub use embedded_wasm_derive::derive_ffi_handler;
 
 type ParseResult<'a, T = ()> = core::result::Result<T, ParseError<'a>>;
@@ -145,6 +155,8 @@ pub enum ErrorKind {
     InvalidHeader,
     /// An invalid section type was found.
     InvalidSection,
+    /// A section was found more than once. Only custom sections can be repeated.
+    DuplicateSection,
     /// An invalid ValType was found.
     UnknownValType,
     /// An unknown export description was found.
@@ -155,8 +167,20 @@ pub enum ErrorKind {
     InvalidCode,
     /// An invalid RefType was found.
     UnknownRefType,
-    /// A 2nd `else` marker was found in the same `if` block.
+    /// An invalid block type was found.
+    InvalidBlockType,
+    /// A data segment with an unknown mode was found.
+    InvalidDataMode,
+    /// An element segment with an unknown mode was found.
+    InvalidElementMode,
+    /// An element segment with an unknown element kind was found.
+    InvalidElementKind,
+    /// The amount of data segments does not match the amount declared in the data count section.
+    DataCountMismatch,
+    /// An `else` marker was found outside of an `if` block, or a 2nd `else` marker was found in the same `if` block.
     DuplicateElse,
+    /// A typed `select` instruction did not have exactly one type.
+    InvalidSelectType,
     /// An unknown instruction was found.
     UnknownInstruction,
     /// An unknown extended instruction was found.
@@ -176,6 +200,9 @@ pub enum ErrorKind {
     /// An integer overflow occured.
     IntegerOverflow(&'static str),
 
+    /// The module was decoded, but is not valid.
+    Invalid(ValidationError),
+
     /// Not implemented.
     Unimplemented,
 }
@@ -186,6 +213,67 @@ impl From<core::str::Utf8Error> for ErrorKind {
     }
 }
 
+/// The module failed validation, see [`ErrorKind::Invalid`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The index of the function that is invalid, or `None` if the error is not in a function body.
+    pub function: Option<usize>,
+    /// The position of the invalid instruction in the body of the function, in the same format as the position of an [`ExecErrorKind::Trap`].
+    ///
+    /// If the values on the stack do not match the results of a block, this is the position of its `else` or `end`.
+    pub position: usize,
+    /// The reason the module is invalid.
+    pub kind: ValidationErrorKind,
+}
+
+/// The error description of [`ValidationError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationErrorKind {
+    /// The operands on the stack do not have the types that an instruction or block expects.
+    TypeMismatch,
+    /// A branch refers to a block that does not exist.
+    UnknownLabel,
+    /// A local was referenced that does not exist.
+    UnknownLocal,
+    /// A global was referenced that does not exist, or that can not be used in a constant expression.
+    UnknownGlobal,
+    /// A function was referenced that does not exist.
+    UnknownFunction,
+    /// A type was referenced that does not exist.
+    UnknownType,
+    /// A table was referenced that does not exist.
+    UnknownTable,
+    /// A memory instruction was used, but the module has no memory.
+    UnknownMemory,
+    /// A data segment was referenced that does not exist, or the module has no data count section.
+    UnknownData,
+    /// An element segment was referenced that does not exist.
+    UnknownElement,
+    /// An export refers to an item that does not exist.
+    UnknownExport,
+    /// `global.set` was used on an immutable global.
+    ImmutableGlobal,
+    /// The alignment of a memory access is larger than the size of the access.
+    InvalidAlignment,
+    /// `ref.func` refers to a function that is not referenced by an element segment, global or export.
+    UndeclaredFunctionReference,
+    /// A constant expression, like the initializer of a global, contains an instruction that is not allowed in constant expressions.
+    InvalidConstantExpression,
+    /// Two exports have the same name.
+    DuplicateExportName,
+    /// The start function has parameters or results.
+    InvalidStartFunction,
+    /// The limits of a table or memory are invalid, e.g. the maximum is smaller than the minimum.
+    InvalidLimits,
+    /// More than one memory was imported or defined.
+    MultipleMemories,
+    /// The amount of function bodies does not match the amount of functions in the function section.
+    FunctionCountMismatch,
+    /// A function declares more locals than the limit of `Wasm::parse_with_locals_limit`, including its parameters.
+    TooManyLocals,
+}
+
 #[derive(Debug)]
 /// An error occured while executing a WASM file.
 pub struct ExecError<'a> {
@@ -201,4 +289,93 @@ pub struct ExecError<'a> {
 pub enum ExecErrorKind {
     /// The given function was not found.
     FunctionNotFound,
+    /// `Process::step` was called after the process returned `ProcessAction::Finished`.
+    ProcessFinished,
+    /// No global is exported with the given name, or `Wasm::set_imported_global` was called with a global that is not imported.
+    GlobalNotFound,
+    /// An immutable global was written to, either by a `global.set` instruction or by `Process::set_global`.
+    GlobalImmutable,
+    /// The value passed to `Process::set_global` or `Wasm::set_imported_global` does not have the type of the global.
+    GlobalTypeMismatch,
+    /// The initial linear memory of the wasm file could not be allocated, or is larger than the limit set with `Wasm::set_memory_limit`.
+    OutOfMemory,
+    /// The host did not push the amount of results that an extern function declares in its type.
+    InvalidExternResultCount {
+        /// The amount of results that the function declares.
+        expected: usize,
+        /// The amount of results that were pushed with `Process::stack_push`.
+        found: usize,
+    },
+    /// The amount of arguments passed to `Wasm::spawn_with_args` does not match the amount of parameters of the function.
+    InvalidArgumentCount {
+        /// The amount of parameters of the function.
+        expected: usize,
+        /// The amount of arguments that were passed.
+        found: usize,
+    },
+    /// The argument at the given index does not have the type of the matching parameter of the function.
+    InvalidArgumentType {
+        /// The index of the argument.
+        index: usize,
+    },
+    /// The signature of the function requested with `Wasm::typed_func` does not match the given parameter and result types.
+    InvalidFunctionSignature,
+    /// A constant expression, like the offset of a data segment, contains an instruction that is not allowed in constant expressions.
+    InvalidConstantExpression,
+    /// The body of a function could not be decoded, or is not valid.
+    ///
+    /// This is only returned with the `lazy` feature, which decodes the body of a function when it is called instead of when the module is parsed.
+    InvalidFunction {
+        /// The index of the function. Imported functions take up the first indices.
+        function: usize,
+        /// The reason the body could not be decoded.
+        error: ErrorKind,
+    },
+    /// Initializing the memory or tables of a new process trapped, e.g. because an active data segment does not fit in memory.
+    InstantiationFailed(Trap),
+    /// The wasm code trapped.
+    Trap {
+        /// The reason of the trap.
+        trap: Trap,
+        /// The index of the function that trapped. Imported functions take up the first indices.
+        function: usize,
+        /// The byte offset of the instruction that trapped into the body of the function.
+        ///
+        /// The offset is counted from the first instruction of the body, so the declarations of the locals are not included.
+        /// The `end` of the function is its last instruction, a function traps there while returning.
+        /// A trap while calling an imported start or entry function is reported at offset 0 of that function.
+        position: usize,
+    },
+}
+
+/// A trap that occured while executing a wasm instruction.
+///
+/// The function and the byte offset of the instruction are reported in [`ExecErrorKind::Trap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Trap {
+    /// An `unreachable` instruction was executed.
+    Unreachable,
+    /// An integer was divided by zero, or the remainder of a division by zero was requested.
+    DivisionByZero,
+    /// The result of an integer operation is not representable, e.g. `i32::MIN / -1`.
+    IntegerOverflow,
+    /// A NaN was converted to an integer.
+    InvalidConversionToInteger,
+    /// Linear memory was accessed outside of its bounds.
+    MemoryOutOfBounds,
+    /// A table was accessed outside of its bounds.
+    TableOutOfBounds,
+    /// `call_indirect` was called with a null table entry.
+    UninitializedElement,
+    /// The function called by `call_indirect` does not have the expected signature.
+    IndirectCallTypeMismatch,
+    /// An instruction popped a value from an empty stack.
+    ///
+    /// Validation rejects modules that can do this, so this indicates a bug in the interpreter.
+    StackUnderflow,
+    /// A function was called while the call stack is at its maximum depth.
+    CallStackOverflow,
+    /// An instruction was executed that is not implemented, e.g. a SIMD instruction without the `simd` feature.
+    UnimplementedInstruction,
 }
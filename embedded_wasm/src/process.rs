@@ -1,161 +1,1158 @@
 use crate::{
-    instruction::{BlockType, ChildInstructions, FuncIdx, Instruction, NumType, RefType, ValType},
-    ExecResult, Vec, Wasm,
+    bytecode::{BodyRef, Branch},
+    float::Float,
+    instruction::{
+        DataIdx, ElemIdx, FuncIdx, GlobalIdx, Instruction, MemArg, NumType, RefType, Signedness,
+        TableIdx, TypeIdx, ValType,
+    },
+    section::{self, ElementMode},
+    wasm::PAGE_SIZE,
+    ExecError, ExecErrorKind, ExecResult, Trap, Vec, Wasm,
 };
+use core::ops::Range;
+
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(test)]
+mod tests;
 
 /// A handle to a running process. This is created by using [`Wasm`]'s `spawn()` function.
 pub struct Process<'a> {
     wasm: &'a Wasm<'a>,
-    program_counter: Vec<ProgramCounter>,
-    // memory: Vec<u8>,
+    program_counter: Vec<ProgramCounter<'a>>,
+    memory: Vec<u8>,
+    /// For every data segment, whether it has been dropped by `data.drop` or by being copied into memory when the process was spawned.
+    dropped_data: Vec<bool>,
+    /// The values of all globals, starting with the imported globals.
+    globals: Vec<Dynamic>,
+    /// The references in all tables, starting with the imported tables.
+    tables: Vec<Vec<Dynamic>>,
+    /// For every element segment, whether it has been dropped by `elem.drop` or when the process was spawned.
+    dropped_elements: Vec<bool>,
     stack: Vec<Dynamic>,
+    /// Set when an extern function is being called. The host should have pushed the results of this function on the next call to `step`.
+    pending_extern: Option<PendingExtern>,
+    /// Set when the start function of the wasm file is imported. This is called by the first call to `step`.
+    pending_start: Option<FuncIdx>,
+    /// Set when the entry function of the process is imported. This is called once the start function returned, with the arguments that are on the stack. The process is finished when it returns.
+    extern_entry: Option<FuncIdx>,
+    /// The bodies of the functions that were called most recently, the most recent last. See `Wasm::set_code_cache_size`.
+    #[cfg(feature = "lazy")]
+    code_cache: Vec<(FuncIdx, BodyRef<'a>)>,
+}
+
+/// The maximum amount of nested function calls. Calling a function beyond this depth traps with `Trap::CallStackOverflow`.
+const MAX_CALL_DEPTH: usize = 1024;
+
+/// The opcode of the `br_table` instruction.
+const BR_TABLE: u8 = 0x0E;
+
+/// An error that occured while executing an instruction. `step` adds the location of the instruction to traps.
+enum StepError {
+    Trap(Trap),
+    Exec(ExecErrorKind),
+}
+
+impl From<Trap> for StepError {
+    fn from(trap: Trap) -> Self {
+        Self::Trap(trap)
+    }
+}
+
+/// Bookkeeping of an extern function call that the host is handling.
+struct PendingExtern {
+    /// The amount of values the extern function should return.
+    result_count: usize,
+    /// The height of the stack after the arguments were popped.
+    stack_height: usize,
 }
 
 impl<'a> Process<'a> {
-    pub(crate) fn new(wasm: &'a Wasm<'a>, idx: FuncIdx) -> Self {
-        Self {
+    pub(crate) fn new(
+        wasm: &'a Wasm<'a>,
+        idx: FuncIdx,
+        args: Vec<Dynamic>,
+    ) -> ExecResult<'a, Self> {
+        let mut process = Self {
             wasm,
-            program_counter: {
-                let mut vec = Vec::with_capacity(1);
-                let code = wasm.get_code(idx);
-                vec.push(ProgramCounter::new(idx, &code.locals));
-                vec
-            },
-            // memory: {
-            //     let mut vec = Vec::with_capacity(wasm.memory.0.min as usize);
-            //     for _ in 0..wasm.memory.0.min {
-            //         vec.push(0u8);
-            //     }
-            //     vec
-            // },
+            program_counter: Vec::with_capacity(1),
+            memory: Vec::new(),
+            dropped_data: alloc::vec![false; wasm.data.len()],
+            globals: wasm.imported_globals().to_vec(),
+            tables: wasm
+                .tables
+                .iter()
+                .map(|table| alloc::vec![table.reftype.into(); table.limits.min as usize])
+                .collect(),
+            dropped_elements: alloc::vec![false; wasm.elements.len()],
             stack: Vec::new(),
+            pending_extern: None,
+            pending_start: None,
+            extern_entry: None,
+            #[cfg(feature = "lazy")]
+            code_cache: Vec::new(),
+        };
+        if wasm.get_import(idx).is_some() {
+            process.stack = args;
+            process.extern_entry = Some(idx);
+        } else {
+            let body = process.body(idx).map_err(|kind| ExecError { wasm, kind })?;
+            process
+                .program_counter
+                .push(ProgramCounter::new(idx, body, args, 0));
         }
+        let min_pages = wasm.memory.as_ref().map_or(0, |m| m.0.min);
+        if process.grow_memory(min_pages).is_none() {
+            return Err(ExecError {
+                wasm,
+                kind: ExecErrorKind::OutOfMemory,
+            });
+        }
+        for global in &wasm.globals {
+            let value = process.eval_const_expr(&global.init)?;
+            process.globals.push(value);
+        }
+        for (index, element) in wasm.elements.iter().enumerate() {
+            let index = ElemIdx(index as u32);
+            match &element.mode {
+                ElementMode::Active { table, offset } => {
+                    let offset = process.eval_const_expr(offset)?.as_i32() as u32;
+                    process
+                        .init_table(*table, index, offset, 0, element.init.len() as u32)
+                        .map_err(|error| ExecError {
+                            wasm,
+                            kind: match error {
+                                StepError::Trap(trap) => ExecErrorKind::InstantiationFailed(trap),
+                                StepError::Exec(kind) => kind,
+                            },
+                        })?;
+                    process.dropped_elements[index.0 as usize] = true;
+                }
+                ElementMode::Declarative => process.dropped_elements[index.0 as usize] = true,
+                ElementMode::Passive => {}
+            }
+        }
+        for (index, data) in wasm.data.iter().enumerate() {
+            if let Some(expression) = &data.expression {
+                let offset = process.eval_const_expr(expression)?.as_i32() as u32;
+                let index = DataIdx(index as u32);
+                process
+                    .init_memory(index, offset, 0, data.bytes.len() as u32)
+                    .map_err(|trap| ExecError {
+                        wasm,
+                        kind: ExecErrorKind::InstantiationFailed(trap),
+                    })?;
+                process.dropped_data[index.0 as usize] = true;
+            }
+        }
+        // The start function runs before the entry function, so its frame is pushed on top
+        if let Some(start) = wasm.start {
+            if wasm.get_import(start).is_some() {
+                process.pending_start = Some(start);
+            } else {
+                let body = process
+                    .body(start)
+                    .map_err(|kind| ExecError { wasm, kind })?;
+                let stack_height = process.stack.len();
+                process.program_counter.push(ProgramCounter::new(
+                    start,
+                    body,
+                    Vec::new(),
+                    stack_height,
+                ));
+            }
+        }
+        Ok(process)
     }
 
-    fn find_instruction<'b>(
-        instructions: &'b [Instruction],
-        idx: &[usize],
-    ) -> Option<&'b Instruction> {
-        let (idx, remaining) = idx.split_first()?;
-        let instruction = instructions.get(*idx)?;
+    /// Branch to the given label, keeping the values that the branch carries on top of the stack.
+    fn branch(&mut self, branch: &Branch) -> Result<(), Trap> {
+        let values = self.stack_split_off(branch.arity as usize)?;
+        let frame = self.program_counter.last_mut().unwrap();
+        self.stack
+            .truncate(frame.stack_height + branch.height as usize);
+        self.stack.extend(values);
+        frame.pc = branch.target as usize;
+        Ok(())
+    }
 
-        if remaining.is_empty() {
-            Some(instruction)
+    /// Call the given function. Imported functions return the action that the host should handle, local functions are entered by pushing a new frame.
+    ///
+    /// `next` is the offset of the instruction that the current frame continues at when a local function returns.
+    fn call(
+        &mut self,
+        function: FuncIdx,
+        next: usize,
+    ) -> Result<Option<ProcessAction<'a>>, StepError> {
+        let ty = self.function_type(function)?;
+        if let Some(import) = self.wasm.get_import(function) {
+            let args = self.stack_split_off(ty.params.len())?;
+            self.pending_extern = Some(PendingExtern {
+                result_count: ty.results.len(),
+                stack_height: self.stack.len(),
+            });
+            Ok(Some(ProcessAction::CallExtern {
+                function: import.name.name,
+                args,
+            }))
         } else {
-            match instruction.get_child_instructions() {
-                ChildInstructions::None => {
-                    panic!("Expected {:?} to return child instructions", instruction)
-                }
-                ChildInstructions::One(children) => Self::find_instruction(children, remaining),
-                ChildInstructions::Two(first, second) => {
-                    let (idx, remaining) = remaining.split_first().unwrap();
-                    match idx {
-                        0 => Self::find_instruction(first, remaining),
-                        1 => Self::find_instruction(second, remaining),
-                        _ => panic!("Unknown index {:?}, expected 0 or 1", idx),
-                    }
-                }
+            if self.program_counter.len() >= MAX_CALL_DEPTH {
+                return Err(Trap::CallStackOverflow.into());
             }
+            let body = self.body(function).map_err(StepError::Exec)?;
+            let args = self.stack_split_off(ty.params.len())?;
+            self.jump(next);
+            self.program_counter
+                .push(ProgramCounter::new(function, body, args, self.stack.len()));
+            Ok(None)
         }
     }
 
-    /// Get a reference to the current instruction. For debugging purposes only, and will be removed in the future.
-    pub fn current_instruction(&self) -> Instruction {
-        let ProgramCounter { func, idx, .. } = self.program_counter.last().unwrap();
-        let code = self.wasm.get_code(*func);
-        match Self::find_instruction(&code.expr, idx) {
-            Some(instruction) => instruction.clone(),
-            None => panic!(
-                "Could not find instruction\ncode: {:?}\nindex: {:?}",
-                code.expr, idx
-            ),
+    /// Call the start function or the imported entry function, which are called outside of an instruction. A trap is reported at the start of `function`, as there may be no frame to report it in.
+    fn call_first(
+        &mut self,
+        function: FuncIdx,
+        next: usize,
+    ) -> Result<ProcessAction<'a>, StepError> {
+        match self.call(function, next) {
+            Ok(action) => Ok(action.unwrap_or(ProcessAction::None)),
+            Err(StepError::Trap(trap)) => Err(StepError::Exec(ExecErrorKind::Trap {
+                trap,
+                function: function.0 as usize,
+                position: 0,
+            })),
+            Err(error) => Err(error),
         }
     }
 
+    /// Get the type of the given function. Validation checks that every called function exists.
+    fn function_type(&self, function: FuncIdx) -> Result<&'a section::Type, StepError> {
+        self.wasm
+            .get_function_type(function)
+            .ok_or(StepError::Exec(ExecErrorKind::FunctionNotFound))
+    }
+
+    /// Get the body of the given local function.
+    #[cfg(not(feature = "lazy"))]
+    fn body(&mut self, function: FuncIdx) -> Result<BodyRef<'a>, ExecErrorKind> {
+        self.wasm
+            .get_code(function)
+            .ok_or(ExecErrorKind::FunctionNotFound)
+    }
+
+    /// Get the body of the given local function, and decode it if it is not in the cache. A full cache evicts the function that was called least recently.
+    #[cfg(feature = "lazy")]
+    fn body(&mut self, function: FuncIdx) -> Result<BodyRef<'a>, ExecErrorKind> {
+        if let Some(index) = self.code_cache.iter().position(|(f, _)| *f == function) {
+            let entry = self.code_cache.remove(index);
+            let body = BodyRef::clone(&entry.1);
+            self.code_cache.push(entry);
+            return Ok(body);
+        }
+        let body = self
+            .wasm
+            .decode_code(function)
+            .ok_or(ExecErrorKind::FunctionNotFound)?
+            .map_err(|error| ExecErrorKind::InvalidFunction {
+                function: function.0 as usize,
+                error: error.kind,
+            })?;
+        let body = BodyRef::new(body);
+        let size = self.wasm.code_cache_size();
+        if size > 0 {
+            if self.code_cache.len() >= size {
+                self.code_cache.remove(0);
+            }
+            self.code_cache.push((function, BodyRef::clone(&body)));
+        }
+        Ok(body)
+    }
+
+    /// Pop a table index from the stack, and look up the function that `call_indirect` should call.
+    fn indirect_function(
+        &mut self,
+        type_idx: TypeIdx,
+        table_idx: TableIdx,
+    ) -> Result<FuncIdx, Trap> {
+        let index = self.stack_pop()?.as_i32() as u32;
+        let function = self.tables[table_idx.0 as usize]
+            .get(index as usize)
+            .ok_or(Trap::TableOutOfBounds)?
+            .as_reference()
+            .ok_or(Trap::UninitializedElement)?;
+        let function = FuncIdx(function);
+        if self.wasm.get_function_type(function) != self.wasm.get_type(type_idx) {
+            return Err(Trap::IndirectCallTypeMismatch);
+        }
+        Ok(function)
+    }
+
+    /// Pop the current frame, and move its results to the stack of the caller.
+    fn return_from_function(&mut self) -> Result<(), StepError> {
+        let frame = self.program_counter.last().unwrap();
+        let result_count = self.function_type(frame.func)?.results.len();
+        let results = self.stack_split_off(result_count)?;
+        let frame = self.program_counter.pop().unwrap();
+        self.stack.truncate(frame.stack_height);
+        self.stack.extend(results);
+        Ok(())
+    }
+
     /// Execute a single step in the wasm runner. See [`ProcessAction`] for correct handling of the return value.
-    pub fn step(&mut self) -> ExecResult<ProcessAction<'a>> {
+    pub fn step(&mut self) -> ExecResult<'a, ProcessAction<'a>> {
+        if self.program_counter.is_empty()
+            && self.extern_entry.is_none()
+            && self.pending_extern.is_none()
+        {
+            return Err(ExecError {
+                wasm: self.wasm,
+                kind: ExecErrorKind::ProcessFinished,
+            });
+        }
+        if let Some(pending) = self.pending_extern.take() {
+            let found = self.stack.len() - pending.stack_height;
+            if found != pending.result_count {
+                return Err(ExecError {
+                    wasm: self.wasm,
+                    kind: ExecErrorKind::InvalidExternResultCount {
+                        expected: pending.result_count,
+                        found,
+                    },
+                });
+            }
+            if self.program_counter.is_empty() && self.extern_entry.is_none() {
+                // The imported entry function returned
+                return Ok(ProcessAction::Finished(core::mem::take(&mut self.stack)));
+            }
+        }
+        self.execute().map_err(|error| match error {
+            StepError::Trap(trap) => self.trap(trap),
+            StepError::Exec(kind) => ExecError {
+                wasm: self.wasm,
+                kind,
+            },
+        })
+    }
+
+    /// Execute the current instruction of the current frame.
+    fn execute(&mut self) -> Result<ProcessAction<'a>, StepError> {
+        if let Some(start) = self.pending_start.take() {
+            // Imported functions return the `CallExtern` action without touching the current frame
+            let pc = self.program_counter.last().map_or(0, |frame| frame.pc);
+            return self.call_first(start, pc);
+        }
+        if self.program_counter.is_empty() {
+            // The start function returned, and the entry function is imported
+            let entry = self.extern_entry.take().unwrap();
+            return self.call_first(entry, 0);
+        }
+        let frame = self.program_counter.last().unwrap();
+        let pc = frame.pc;
+        // The body is shared with the frame, not borrowed from `self`
+        let body = BodyRef::clone(&frame.body);
+        // The jumps of a `br_table` are resolved, decoding its labels would only allocate
+        if body.code.get(pc) == Some(&BR_TABLE) {
+            let jumps = body.jumps(pc);
+            // The last jump is the default
+            let i = self.stack_pop()?.as_i32() as u32 as usize;
+            self.branch(&jumps.get(i).unwrap_or(jumps.last().unwrap()).branch)?;
+            return Ok(ProcessAction::None);
+        }
+        let (instruction, next) = body.instruction(pc).map_err(|error| {
+            StepError::Exec(ExecErrorKind::InvalidFunction {
+                function: frame.func.0 as usize,
+                error: error.kind,
+            })
+        })?;
         let mut result = ProcessAction::None;
         let mut do_step = true;
-        match self.current_instruction() {
+        match instruction {
+            Instruction::Block { .. }
+            | Instruction::Loop { .. }
+            | Instruction::If { .. }
+            | Instruction::Else
+            | Instruction::End
+            | Instruction::Branch { .. }
+            | Instruction::BranchIf { .. }
+            | Instruction::Return => {
+                return self.execute_control(&instruction, &body, pc, next);
+            }
             Instruction::I32Const(val) => {
                 self.stack_push(val);
             }
-            Instruction::Call { function } => {
-                if let Some(import) = self.wasm.get_import(function) {
-                    result = ProcessAction::CallExtern {
-                        function: import.name.name,
-                        args: core::mem::take(&mut self.stack),
-                    };
-                } else {
-                    unimplemented!("Calling local function");
+            Instruction::Call { function } => match self.call(function, next)? {
+                Some(action) => result = action,
+                None => do_step = false,
+            },
+            Instruction::CallIndirect {
+                type_idx,
+                table_idx,
+            } => {
+                let function = self.indirect_function(type_idx, table_idx)?;
+                match self.call(function, next)? {
+                    Some(action) => result = action,
+                    None => do_step = false,
                 }
             }
             Instruction::LocalSet(idx) => {
+                let val = self.stack_pop()?;
                 let ProgramCounter { locals, .. } = self.program_counter.last_mut().unwrap();
-                locals[idx.0] = self.stack.pop().unwrap();
+                locals[idx.0 as usize] = val;
             }
             Instruction::LocalGet(idx) => {
                 let ProgramCounter { locals, .. } = self.program_counter.last().unwrap();
-                let val = locals[idx.0].clone();
+                let val = locals[idx.0 as usize].clone();
                 self.stack_push(val);
             }
-            Instruction::Loop { bt, inner: _ } => {
-                assert_eq!(bt, BlockType::Empty);
-                self.program_counter.last_mut().unwrap().idx.push(0);
-                do_step = false;
-            }
-            Instruction::Branch { mut index } => {
-                let ProgramCounter { idx, func, .. } = self.program_counter.last_mut().unwrap();
-                let code = self.wasm.get_code(*func);
-                loop {
-                    idx.pop();
-                    if index.0 > 0 {
-                        index.0 -= 1;
-                    } else {
-                        break;
+            Instruction::LocalTee(idx) => {
+                let ProgramCounter { locals, .. } = self.program_counter.last_mut().unwrap();
+                locals[idx.0 as usize] = self.stack.last().ok_or(Trap::StackUnderflow)?.clone();
+            }
+            Instruction::Drop => {
+                self.stack_pop()?;
+            }
+            Instruction::Select | Instruction::SelectVal { .. } => {
+                let condition = self.stack_pop()?.as_i32();
+                let val2 = self.stack_pop()?;
+                let val1 = self.stack_pop()?;
+                self.stack_push(if condition != 0 { val1 } else { val2 });
+            }
+            Instruction::Load { numtype, memarg } => {
+                match numtype {
+                    NumType::I32 => self.load(&memarg, i32::from_le_bytes),
+                    NumType::I64 => self.load(&memarg, i64::from_le_bytes),
+                    NumType::F32 => self.load(&memarg, f32::from_le_bytes),
+                    NumType::F64 => self.load(&memarg, f64::from_le_bytes),
+                }?;
+            }
+            Instruction::Load8 {
+                numtype,
+                memarg,
+                signedness,
+            } => {
+                match (numtype, signedness) {
+                    (NumType::I32, Signedness::Signed) => {
+                        self.load(&memarg, |b| i8::from_le_bytes(b) as i32)
                     }
-                }
-                match Self::find_instruction(&code.expr, idx).unwrap().clone() {
-                    Instruction::Loop { bt, .. } => {
-                        assert_eq!(bt, BlockType::Empty);
-                        idx.push(0);
-                        do_step = false;
+                    (NumType::I32, Signedness::Unsigned) => {
+                        self.load(&memarg, |b| u8::from_le_bytes(b) as i32)
                     }
-                    x if x.get_child_instructions().is_none() => {}
-                    x => unimplemented!("Loop handling on {:?}", x),
+                    (_, Signedness::Signed) => self.load(&memarg, |b| i8::from_le_bytes(b) as i64),
+                    (_, Signedness::Unsigned) => {
+                        self.load(&memarg, |b| u8::from_le_bytes(b) as i64)
+                    }
+                }?;
+            }
+            Instruction::Load16 {
+                numtype,
+                memarg,
+                signedness,
+            } => {
+                match (numtype, signedness) {
+                    (NumType::I32, Signedness::Signed) => {
+                        self.load(&memarg, |b| i16::from_le_bytes(b) as i32)
+                    }
+                    (NumType::I32, Signedness::Unsigned) => {
+                        self.load(&memarg, |b| u16::from_le_bytes(b) as i32)
+                    }
+                    (_, Signedness::Signed) => self.load(&memarg, |b| i16::from_le_bytes(b) as i64),
+                    (_, Signedness::Unsigned) => {
+                        self.load(&memarg, |b| u16::from_le_bytes(b) as i64)
+                    }
+                }?;
+            }
+            Instruction::Load32 { memarg, signedness } => {
+                match signedness {
+                    Signedness::Signed => self.load(&memarg, |b| i32::from_le_bytes(b) as i64),
+                    Signedness::Unsigned => self.load(&memarg, |b| u32::from_le_bytes(b) as i64),
+                }?;
+            }
+            Instruction::Store { numtype, memarg } => {
+                let len = match numtype {
+                    NumType::I32 | NumType::F32 => 4,
+                    NumType::I64 | NumType::F64 => 8,
+                };
+                self.store(&memarg, len)?;
+            }
+            Instruction::Store8 { memarg, .. } => {
+                self.store(&memarg, 1)?;
+            }
+            Instruction::Store16 { memarg, .. } => {
+                self.store(&memarg, 2)?;
+            }
+            Instruction::Store32 { memarg, .. } => {
+                self.store(&memarg, 4)?;
+            }
+            Instruction::MemorySize => {
+                self.stack_push((self.memory.len() / PAGE_SIZE) as i32);
+            }
+            Instruction::MemoryGrow => {
+                let delta = self.stack_pop()?.as_i32() as u32;
+                let result = match self.grow_memory(delta) {
+                    Some(previous) => previous as i32,
+                    None => -1,
+                };
+                self.stack_push(result);
+            }
+            Instruction::MemoryInit { index } => {
+                let len = self.stack_pop()?.as_i32() as u32;
+                let source = self.stack_pop()?.as_i32() as u32;
+                let destination = self.stack_pop()?.as_i32() as u32;
+                self.init_memory(index, destination, source, len)?;
+            }
+            Instruction::DataDrop { index } => {
+                self.dropped_data[index.0 as usize] = true;
+            }
+            Instruction::RefNull { .. } => {
+                self.stack_push(Dynamic::from_reference(None));
+            }
+            Instruction::RefIsNull => {
+                let is_null = self.stack_pop()?.as_reference().is_none();
+                self.stack_push(is_null as i32);
+            }
+            Instruction::RefFunc { index } => {
+                self.stack_push(Dynamic::from_reference(Some(index.0)));
+            }
+            Instruction::TableGet { index } => {
+                let i = self.stack_pop()?.as_i32() as u32;
+                let value = self.tables[index.0 as usize]
+                    .get(i as usize)
+                    .cloned()
+                    .ok_or(Trap::TableOutOfBounds)?;
+                self.stack_push(value);
+            }
+            Instruction::TableSet { index } => {
+                let value = self.stack_pop()?;
+                let i = self.stack_pop()?.as_i32() as u32;
+                match self.tables[index.0 as usize].get_mut(i as usize) {
+                    Some(entry) => *entry = value,
+                    None => return Err(Trap::TableOutOfBounds.into()),
+                }
+            }
+            Instruction::TableSize { x } => {
+                self.stack_push(self.tables[x.0 as usize].len() as i32);
+            }
+            Instruction::TableGrow { x } => {
+                let delta = self.stack_pop()?.as_i32() as u32;
+                let value = self.stack_pop()?;
+                let result = match self.grow_table(x, delta, value) {
+                    Some(previous) => previous as i32,
+                    None => -1,
+                };
+                self.stack_push(result);
+            }
+            Instruction::TableFill { x } => {
+                let len = self.stack_pop()?.as_i32() as u32;
+                let value = self.stack_pop()?;
+                let i = self.stack_pop()?.as_i32() as u32;
+                self.tables[x.0 as usize]
+                    .get_mut(i as usize..)
+                    .and_then(|table| table.get_mut(..len as usize))
+                    .ok_or(Trap::TableOutOfBounds)?
+                    .fill(value);
+            }
+            Instruction::TableCopy { x, y } => {
+                let len = self.stack_pop()?.as_i32() as u32;
+                let source = self.stack_pop()?.as_i32() as u32;
+                let destination = self.stack_pop()?.as_i32() as u32;
+                self.copy_table(x, y, destination, source, len)?;
+            }
+            Instruction::TableInit { y, x } => {
+                let len = self.stack_pop()?.as_i32() as u32;
+                let source = self.stack_pop()?.as_i32() as u32;
+                let destination = self.stack_pop()?.as_i32() as u32;
+                self.init_table(x, y, destination, source, len)?;
+            }
+            Instruction::TableDrop { x } => {
+                self.dropped_elements[x.0 as usize] = true;
+            }
+            Instruction::MemoryCopy => {
+                let len = self.stack_pop()?.as_i32() as u32;
+                let source = self.stack_pop()?.as_i32() as u32;
+                let destination = self.stack_pop()?.as_i32() as u32;
+                self.copy_memory(destination, source, len)?;
+            }
+            Instruction::MemoryFill => {
+                let len = self.stack_pop()?.as_i32() as u32;
+                let value = self.stack_pop()?.as_i32() as u8;
+                let destination = self.stack_pop()?.as_i32() as u32;
+                self.memory
+                    .get_mut(destination as usize..)
+                    .and_then(|memory| memory.get_mut(..len as usize))
+                    .ok_or(Trap::MemoryOutOfBounds)?
+                    .fill(value);
+            }
+            Instruction::GlobalGet(idx) => {
+                let value = self.globals[idx.0 as usize].clone();
+                self.stack_push(value);
+            }
+            Instruction::GlobalSet(idx) => {
+                if !self.wasm.global_type(idx).mutable {
+                    return Err(StepError::Exec(ExecErrorKind::GlobalImmutable));
                 }
+                self.globals[idx.0 as usize] = self.stack_pop()?;
             }
-            x => unimplemented!("Unimplemented instruction: {:?}", x),
+            Instruction::Nop => {}
+            Instruction::Unreachable => {
+                return Err(Trap::Unreachable.into());
+            }
+            #[cfg(feature = "simd")]
+            Instruction::Vector(ref instruction) => self.execute_vector(instruction)?,
+            _ => self.execute_numeric(&instruction)?,
         }
         if do_step {
-            *self
-                .program_counter
-                .last_mut()
-                .unwrap()
-                .idx
-                .last_mut()
-                .unwrap() += 1;
+            self.jump(next);
         }
         Ok(result)
     }
 
+    /// Execute the control instruction at `pc` in the body of the current frame. `next` is the offset of the next instruction.
+    fn execute_control(
+        &mut self,
+        instruction: &Instruction,
+        body: &BodyRef<'a>,
+        pc: usize,
+        next: usize,
+    ) -> Result<ProcessAction<'a>, StepError> {
+        match instruction {
+            Instruction::Block { .. } | Instruction::Loop { .. } => self.jump(next),
+            Instruction::End if next < body.code.len() => self.jump(next),
+            Instruction::If { .. } => {
+                if self.stack_pop()?.as_i32() != 0 {
+                    self.jump(next);
+                } else {
+                    self.jump(body.jump(pc).target as usize);
+                }
+            }
+            Instruction::Else => self.jump(body.jump(pc).target as usize),
+            Instruction::Branch { .. } => self.branch(body.jump(pc))?,
+            Instruction::BranchIf { .. } => {
+                if self.stack_pop()?.as_i32() != 0 {
+                    self.branch(body.jump(pc))?;
+                } else {
+                    self.jump(next);
+                }
+            }
+            // A `return`, or the `end` of the function
+            _ => {
+                // The start function returns to an imported entry function
+                if self.program_counter.len() > 1 || self.extern_entry.is_some() {
+                    self.return_from_function()?;
+                } else {
+                    let frame = self.program_counter.last().unwrap();
+                    let result_count = self.function_type(frame.func)?.results.len();
+                    let results = self.stack_split_off(result_count)?;
+                    self.program_counter.pop();
+                    return Ok(ProcessAction::Finished(results));
+                }
+            }
+        }
+        Ok(ProcessAction::None)
+    }
+
+    /// Move the current frame to the instruction at the given offset.
+    fn jump(&mut self, target: usize) {
+        self.program_counter.last_mut().unwrap().pc = target;
+    }
+
     /// Push a value onto the stack. This should only be called when [`ProcessAction`] `CallExten` is returned from `step`
     pub fn stack_push(&mut self, val: impl Into<Dynamic>) {
         self.stack.push(val.into());
     }
+
+    /// Grow the linear memory by `delta` pages. Returns the previous amount of pages, or `None` if the memory could not be grown.
+    fn grow_memory(&mut self, delta: u32) -> Option<u32> {
+        let pages = (self.memory.len() / PAGE_SIZE) as u32;
+        let new_pages = pages.checked_add(delta)?;
+        if new_pages > self.wasm.max_memory_pages() {
+            return None;
+        }
+        let new_len = (new_pages as usize).checked_mul(PAGE_SIZE)?;
+        self.memory
+            .try_reserve_exact(new_len - self.memory.len())
+            .ok()?;
+        self.memory.resize(new_len, 0);
+        Some(pages)
+    }
+
+    /// Get the value of the global that is exported with the given `name`.
+    pub fn get_global(&self, name: &str) -> ExecResult<'a, Dynamic> {
+        let idx = self.find_exported_global(name)?;
+        Ok(self.globals[idx.0 as usize].clone())
+    }
+
+    /// Set the value of the global that is exported with the given `name`. This fails if the global is not mutable, or if `value` does not have the type of the global.
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) -> ExecResult<'a, ()> {
+        let idx = self.find_exported_global(name)?;
+        let global_type = self.wasm.global_type(idx);
+        let value = value.into();
+        let kind = if !global_type.mutable {
+            ExecErrorKind::GlobalImmutable
+        } else if value.val_type() != global_type.valtype {
+            ExecErrorKind::GlobalTypeMismatch
+        } else {
+            self.globals[idx.0 as usize] = value.into();
+            return Ok(());
+        };
+        Err(ExecError {
+            wasm: self.wasm,
+            kind,
+        })
+    }
+
+    fn find_exported_global(&self, name: &str) -> ExecResult<'a, GlobalIdx> {
+        self.wasm.find_exported_global(name).ok_or(ExecError {
+            wasm: self.wasm,
+            kind: ExecErrorKind::GlobalNotFound,
+        })
+    }
+
+    /// Copy `len` bytes, starting at `source` in the given data segment, to `destination` in memory.
+    fn init_memory(
+        &mut self,
+        index: DataIdx,
+        destination: u32,
+        source: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        let bytes = if self.dropped_data[index.0 as usize] {
+            &[]
+        } else {
+            self.wasm.data[index.0 as usize].bytes
+        };
+        let source = bytes
+            .get(source as usize..)
+            .and_then(|bytes| bytes.get(..len as usize))
+            .ok_or(Trap::MemoryOutOfBounds)?;
+        self.memory
+            .get_mut(destination as usize..)
+            .and_then(|memory| memory.get_mut(..len as usize))
+            .ok_or(Trap::MemoryOutOfBounds)?
+            .copy_from_slice(source);
+        Ok(())
+    }
+
+    /// Grow the given table by `delta` entries, that are set to `value`. Returns the previous size of the table, or `None` if the table could not be grown.
+    fn grow_table(&mut self, index: TableIdx, delta: u32, value: Dynamic) -> Option<u32> {
+        let max = self.wasm.tables[index.0 as usize]
+            .limits
+            .max
+            .unwrap_or(u32::MAX);
+        let table = &mut self.tables[index.0 as usize];
+        let len = table.len() as u32;
+        let new_len = len.checked_add(delta)?;
+        if new_len > max {
+            return None;
+        }
+        table.try_reserve_exact(delta as usize).ok()?;
+        table.resize(new_len as usize, value);
+        Some(len)
+    }
+
+    /// Copy `len` references from `source` in table `y` to `destination` in table `x`.
+    fn copy_table(
+        &mut self,
+        x: TableIdx,
+        y: TableIdx,
+        destination: u32,
+        source: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        let in_bounds =
+            |table: &Vec<Dynamic>, start: u32| start as u64 + len as u64 <= table.len() as u64;
+        if !in_bounds(&self.tables[y.0 as usize], source)
+            || !in_bounds(&self.tables[x.0 as usize], destination)
+        {
+            return Err(Trap::TableOutOfBounds);
+        }
+        let (source, destination, len) = (source as usize, destination as usize, len as usize);
+        // Copy through a buffer, as the source and destination may overlap
+        let references = self.tables[y.0 as usize][source..source + len].to_vec();
+        self.tables[x.0 as usize][destination..destination + len].clone_from_slice(&references);
+        Ok(())
+    }
+
+    /// Copy `len` references, starting at `source` in the given element segment, to `destination` in the given table.
+    fn init_table(
+        &mut self,
+        table: TableIdx,
+        index: ElemIdx,
+        destination: u32,
+        source: u32,
+        len: u32,
+    ) -> Result<(), StepError> {
+        let init: &[Vec<Instruction>] = if self.dropped_elements[index.0 as usize] {
+            &[]
+        } else {
+            &self.wasm.elements[index.0 as usize].init
+        };
+        let init = init
+            .get(source as usize..)
+            .and_then(|init| init.get(..len as usize))
+            .ok_or(Trap::TableOutOfBounds)?;
+        let mut references = Vec::with_capacity(init.len());
+        for expression in init {
+            let reference = self
+                .eval_const_expr(expression)
+                .map_err(|error| StepError::Exec(error.kind))?;
+            references.push(reference);
+        }
+        self.tables[table.0 as usize]
+            .get_mut(destination as usize..)
+            .and_then(|table| table.get_mut(..references.len()))
+            .ok_or(Trap::TableOutOfBounds)?
+            .clone_from_slice(&references);
+        Ok(())
+    }
+
+    /// Copy `len` bytes from `source` to `destination` in memory. The ranges are allowed to overlap.
+    fn copy_memory(&mut self, destination: u32, source: u32, len: u32) -> Result<(), Trap> {
+        let in_bounds = |start: u32| start as u64 + len as u64 <= self.memory.len() as u64;
+        if !in_bounds(source) || !in_bounds(destination) {
+            return Err(Trap::MemoryOutOfBounds);
+        }
+        let source = source as usize;
+        self.memory
+            .copy_within(source..source + len as usize, destination as usize);
+        Ok(())
+    }
+
+    /// Evaluate a constant expression, like the offset of a data segment.
+    fn eval_const_expr(&self, expression: &[Instruction]) -> ExecResult<'a, Dynamic> {
+        match expression {
+            [Instruction::I32Const(value)] => Ok((*value).into()),
+            [Instruction::I64Const(value)] => Ok((*value).into()),
+            [Instruction::F32Const(value)] => Ok((*value).into()),
+            [Instruction::F64Const(value)] => Ok((*value).into()),
+            [Instruction::RefNull { .. }] => Ok(Dynamic::from_reference(None)),
+            [Instruction::RefFunc { index }] => Ok(Dynamic::from_reference(Some(index.0))),
+            // Only globals that are already initialized can be referenced
+            [Instruction::GlobalGet(idx)] if (idx.0 as usize) < self.globals.len() => {
+                Ok(self.globals[idx.0 as usize].clone())
+            }
+            _ => Err(ExecError {
+                wasm: self.wasm,
+                kind: ExecErrorKind::InvalidConstantExpression,
+            }),
+        }
+    }
+
+    /// Pop an address from the stack and return the range of `len` bytes in memory that `memarg` points to.
+    fn memory_range(&mut self, memarg: &MemArg, len: usize) -> Result<Range<usize>, Trap> {
+        let address = self.stack_pop()?.as_i32() as u32 as u64;
+        let start = address + memarg.offset as u64;
+        let end = start + len as u64;
+        if end > self.memory.len() as u64 {
+            return Err(Trap::MemoryOutOfBounds);
+        }
+        Ok(start as usize..end as usize)
+    }
+
+    /// Pop an address from the stack, and push the value that is loaded from memory.
+    fn load<const N: usize, T: Into<Dynamic>>(
+        &mut self,
+        memarg: &MemArg,
+        f: impl FnOnce([u8; N]) -> T,
+    ) -> Result<(), Trap> {
+        let range = self.memory_range(memarg, N)?;
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.memory[range]);
+        self.stack_push(f(bytes));
+        Ok(())
+    }
+
+    /// Pop a value and an address from the stack, and store the first `len` bytes of the value in memory.
+    fn store(&mut self, memarg: &MemArg, len: usize) -> Result<(), Trap> {
+        let value = self.stack_pop()?;
+        let range = self.memory_range(memarg, len)?;
+        self.memory[range].copy_from_slice(&value.0[..len]);
+        Ok(())
+    }
+
+    fn stack_pop(&mut self) -> Result<Dynamic, Trap> {
+        self.stack.pop().ok_or(Trap::StackUnderflow)
+    }
+
+    /// Pop the top `count` values from the stack, the top value is last.
+    fn stack_split_off(&mut self, count: usize) -> Result<Vec<Dynamic>, Trap> {
+        let at = self
+            .stack
+            .len()
+            .checked_sub(count)
+            .ok_or(Trap::StackUnderflow)?;
+        Ok(self.stack.split_off(at))
+    }
+
+    /// Create an error for a trap at the current instruction of the current frame.
+    fn trap(&self, trap: Trap) -> ExecError<'a> {
+        let (function, position) = self
+            .program_counter
+            .last()
+            .map_or((0, 0), |frame| (frame.func.0 as usize, frame.pc));
+        ExecError {
+            wasm: self.wasm,
+            kind: ExecErrorKind::Trap {
+                trap,
+                function,
+                position,
+            },
+        }
+    }
+
+    fn unop<T: FromDynamic, R: Into<Dynamic>>(
+        &mut self,
+        f: impl FnOnce(T) -> R,
+    ) -> Result<(), Trap> {
+        let a = T::from_dynamic(&self.stack_pop()?);
+        self.stack_push(f(a));
+        Ok(())
+    }
+
+    fn try_unop<T: FromDynamic, R: Into<Dynamic>>(
+        &mut self,
+        f: impl FnOnce(T) -> Result<R, Trap>,
+    ) -> Result<(), Trap> {
+        let a = T::from_dynamic(&self.stack_pop()?);
+        self.stack_push(f(a)?);
+        Ok(())
+    }
+
+    fn binop<T: FromDynamic, R: Into<Dynamic>>(
+        &mut self,
+        f: impl FnOnce(T, T) -> R,
+    ) -> Result<(), Trap> {
+        let b = T::from_dynamic(&self.stack_pop()?);
+        let a = T::from_dynamic(&self.stack_pop()?);
+        self.stack_push(f(a, b));
+        Ok(())
+    }
+
+    fn try_binop<T: FromDynamic, R: Into<Dynamic>>(
+        &mut self,
+        f: impl FnOnce(T, T) -> Result<R, Trap>,
+    ) -> Result<(), Trap> {
+        let b = T::from_dynamic(&self.stack_pop()?);
+        let a = T::from_dynamic(&self.stack_pop()?);
+        self.stack_push(f(a, b)?);
+        Ok(())
+    }
+
+    /// Execute a numeric instruction.
+    fn execute_numeric(&mut self, instruction: &Instruction) -> Result<(), Trap> {
+        use Instruction::*;
+        match instruction {
+            I64Const(val) => self.stack_push(*val),
+            F32Const(val) => self.stack_push(*val),
+            F64Const(val) => self.stack_push(*val),
+
+            I32EqualZero => self.unop(|a: i32| (a == 0) as i32)?,
+            I32Equals => self.binop(|a: i32, b| (a == b) as i32)?,
+            I32NotEquals => self.binop(|a: i32, b| (a != b) as i32)?,
+            I32LessThanSigned => self.binop(|a: i32, b| (a < b) as i32)?,
+            I32LessThanUnsigned => self.binop(|a: i32, b| ((a as u32) < (b as u32)) as i32)?,
+            I32GreaterThanSigned => self.binop(|a: i32, b| (a > b) as i32)?,
+            I32GreaterThanUnsigned => self.binop(|a: i32, b| ((a as u32) > (b as u32)) as i32)?,
+            I32LessOrEqualToSigned => self.binop(|a: i32, b| (a <= b) as i32)?,
+            I32LessOrEqualToUnsigned => {
+                self.binop(|a: i32, b| ((a as u32) <= (b as u32)) as i32)?
+            }
+            I32GreaterOrEqualToSigned => self.binop(|a: i32, b| (a >= b) as i32)?,
+            I32GreaterOrEqualToUnsigned => {
+                self.binop(|a: i32, b| ((a as u32) >= (b as u32)) as i32)?
+            }
+
+            I64EqualZero => self.unop(|a: i64| (a == 0) as i32)?,
+            I64Equals => self.binop(|a: i64, b| (a == b) as i32)?,
+            I64NotEquals => self.binop(|a: i64, b| (a != b) as i32)?,
+            I64LessThanSigned => self.binop(|a: i64, b| (a < b) as i32)?,
+            I64LessThanUnsigned => self.binop(|a: i64, b| ((a as u64) < (b as u64)) as i32)?,
+            I64GreaterThanSigned => self.binop(|a: i64, b| (a > b) as i32)?,
+            I64GreaterThanUnsigned => self.binop(|a: i64, b| ((a as u64) > (b as u64)) as i32)?,
+            I64LessOrEqualToSigned => self.binop(|a: i64, b| (a <= b) as i32)?,
+            I64LessOrEqualToUnsigned => {
+                self.binop(|a: i64, b| ((a as u64) <= (b as u64)) as i32)?
+            }
+            I64GreaterOrEqualToSigned => self.binop(|a: i64, b| (a >= b) as i32)?,
+            I64GreaterOrEqualToUnsigned => {
+                self.binop(|a: i64, b| ((a as u64) >= (b as u64)) as i32)?
+            }
+
+            F32Equals => self.binop(|a: f32, b| (a == b) as i32)?,
+            F32NotEquals => self.binop(|a: f32, b| (a != b) as i32)?,
+            F32LessThan => self.binop(|a: f32, b| (a < b) as i32)?,
+            F32GreaterThan => self.binop(|a: f32, b| (a > b) as i32)?,
+            F32LessOrEqualTo => self.binop(|a: f32, b| (a <= b) as i32)?,
+            F32GreaterOrEqualTo => self.binop(|a: f32, b| (a >= b) as i32)?,
+
+            F64Equals => self.binop(|a: f64, b| (a == b) as i32)?,
+            F64NotEquals => self.binop(|a: f64, b| (a != b) as i32)?,
+            F64LessThan => self.binop(|a: f64, b| (a < b) as i32)?,
+            F64GreaterThan => self.binop(|a: f64, b| (a > b) as i32)?,
+            F64LessOrEqualTo => self.binop(|a: f64, b| (a <= b) as i32)?,
+            F64GreaterOrEqualTo => self.binop(|a: f64, b| (a >= b) as i32)?,
+
+            I32CountLeadingZeroBits => self.unop(|a: i32| a.leading_zeros() as i32)?,
+            I32CountTrailingZeroBits => self.unop(|a: i32| a.trailing_zeros() as i32)?,
+            I32CountNonZeroBits => self.unop(|a: i32| a.count_ones() as i32)?,
+            I32Add => self.binop(i32::wrapping_add)?,
+            I32Sub => self.binop(i32::wrapping_sub)?,
+            I32Mul => self.binop(i32::wrapping_mul)?,
+            I32DivSigned => self.try_binop(|a: i32, b| match (a, b) {
+                (_, 0) => Err(Trap::DivisionByZero),
+                (i32::MIN, -1) => Err(Trap::IntegerOverflow),
+                (a, b) => Ok(a / b),
+            })?,
+            I32DivUnsigned => self.try_binop(|a: i32, b| match b {
+                0 => Err(Trap::DivisionByZero),
+                b => Ok(((a as u32) / (b as u32)) as i32),
+            })?,
+            I32RemainderSigned => self.try_binop(|a: i32, b| match b {
+                0 => Err(Trap::DivisionByZero),
+                b => Ok(a.wrapping_rem(b)),
+            })?,
+            I32RemainderUnsigned => self.try_binop(|a: i32, b| match b {
+                0 => Err(Trap::DivisionByZero),
+                b => Ok(((a as u32) % (b as u32)) as i32),
+            })?,
+            I32LogicalAnd => self.binop(|a: i32, b| a & b)?,
+            I32LogicalOr => self.binop(|a: i32, b| a | b)?,
+            I32LogicalXor => self.binop(|a: i32, b| a ^ b)?,
+            I32ShiftLeft => self.binop(|a: i32, b| a.wrapping_shl(b as u32))?,
+            I32ShiftRightSigned => self.binop(|a: i32, b| a.wrapping_shr(b as u32))?,
+            I32ShiftRightUnsigned => {
+                self.binop(|a: i32, b| (a as u32).wrapping_shr(b as u32) as i32)?
+            }
+            I32RotateLeft => self.binop(|a: i32, b| a.rotate_left(b as u32))?,
+            I32RotateRight => self.binop(|a: i32, b| a.rotate_right(b as u32))?,
+
+            I64CountLeadingZeroBits => self.unop(|a: i64| a.leading_zeros() as i64)?,
+            I64CountTrailingZeroBits => self.unop(|a: i64| a.trailing_zeros() as i64)?,
+            I64CountNonZeroBits => self.unop(|a: i64| a.count_ones() as i64)?,
+            I64Add => self.binop(i64::wrapping_add)?,
+            I64Sub => self.binop(i64::wrapping_sub)?,
+            I64Mul => self.binop(i64::wrapping_mul)?,
+            I64DivSigned => self.try_binop(|a: i64, b| match (a, b) {
+                (_, 0) => Err(Trap::DivisionByZero),
+                (i64::MIN, -1) => Err(Trap::IntegerOverflow),
+                (a, b) => Ok(a / b),
+            })?,
+            I64DivUnsigned => self.try_binop(|a: i64, b| match b {
+                0 => Err(Trap::DivisionByZero),
+                b => Ok(((a as u64) / (b as u64)) as i64),
+            })?,
+            I64RemainderSigned => self.try_binop(|a: i64, b| match b {
+                0 => Err(Trap::DivisionByZero),
+                b => Ok(a.wrapping_rem(b)),
+            })?,
+            I64RemainderUnsigned => self.try_binop(|a: i64, b| match b {
+                0 => Err(Trap::DivisionByZero),
+                b => Ok(((a as u64) % (b as u64)) as i64),
+            })?,
+            I64LogicalAnd => self.binop(|a: i64, b| a & b)?,
+            I64LogicalOr => self.binop(|a: i64, b| a | b)?,
+            I64LogicalXor => self.binop(|a: i64, b| a ^ b)?,
+            I64ShiftLeft => self.binop(|a: i64, b| a.wrapping_shl(b as u32))?,
+            I64ShiftRightSigned => self.binop(|a: i64, b| a.wrapping_shr(b as u32))?,
+            I64ShiftRightUnsigned => {
+                self.binop(|a: i64, b| (a as u64).wrapping_shr(b as u32) as i64)?
+            }
+            I64RotateLeft => self.binop(|a: i64, b| a.rotate_left(b as u32))?,
+            I64RotateRight => self.binop(|a: i64, b| a.rotate_right(b as u32))?,
+
+            F32Abs => self.unop(f32::wasm_abs)?,
+            F32Neg => self.unop(f32::wasm_neg)?,
+            F32Ceil => self.unop(f32::wasm_ceil)?,
+            F32Floor => self.unop(f32::wasm_floor)?,
+            F32Trunc => self.unop(f32::wasm_trunc)?,
+            F32Nearest => self.unop(f32::wasm_nearest)?,
+            F32Sqrt => self.unop(f32::wasm_sqrt)?,
+            F32Add => self.binop(|a: f32, b| a + b)?,
+            F32Sub => self.binop(|a: f32, b| a - b)?,
+            F32Mul => self.binop(|a: f32, b| a * b)?,
+            F32Div => self.binop(|a: f32, b| a / b)?,
+            F32Min => self.binop(f32::wasm_min)?,
+            F32Max => self.binop(f32::wasm_max)?,
+            F32CopySign => self.binop(f32::wasm_copysign)?,
+
+            F64Abs => self.unop(f64::wasm_abs)?,
+            F64Neg => self.unop(f64::wasm_neg)?,
+            F64Ceil => self.unop(f64::wasm_ceil)?,
+            F64Floor => self.unop(f64::wasm_floor)?,
+            F64Trunc => self.unop(f64::wasm_trunc)?,
+            F64Nearest => self.unop(f64::wasm_nearest)?,
+            F64Sqrt => self.unop(f64::wasm_sqrt)?,
+            F64Add => self.binop(|a: f64, b| a + b)?,
+            F64Sub => self.binop(|a: f64, b| a - b)?,
+            F64Mul => self.binop(|a: f64, b| a * b)?,
+            F64Div => self.binop(|a: f64, b| a / b)?,
+            F64Min => self.binop(f64::wasm_min)?,
+            F64Max => self.binop(f64::wasm_max)?,
+            F64CopySign => self.binop(f64::wasm_copysign)?,
+
+            I32WrapI64 => self.unop(|a: i64| a as i32)?,
+            I32TruncF32Signed => self.try_unop(f32::trunc_i32)?,
+            I32TruncF32Unsigned => self.try_unop(|a: f32| a.trunc_u32().map(|a| a as i32))?,
+            I32TruncF64Signed => self.try_unop(f64::trunc_i32)?,
+            I32TruncF64Unsigned => self.try_unop(|a: f64| a.trunc_u32().map(|a| a as i32))?,
+
+            I64ExtendI32Signed => self.unop(|a: i32| a as i64)?,
+            I64ExtendI32Unsigned => self.unop(|a: i32| a as u32 as i64)?,
+            I64TruncF32Signed => self.try_unop(f32::trunc_i64)?,
+            I64TruncF32Unsigned => self.try_unop(|a: f32| a.trunc_u64().map(|a| a as i64))?,
+            I64TruncF64Signed => self.try_unop(f64::trunc_i64)?,
+            I64TruncF64Unsigned => self.try_unop(|a: f64| a.trunc_u64().map(|a| a as i64))?,
+
+            F32ConvertI32Signed => self.unop(|a: i32| a as f32)?,
+            F32ConvertI32Unsigned => self.unop(|a: i32| a as u32 as f32)?,
+            F32ConvertI64Signed => self.unop(|a: i64| a as f32)?,
+            F32ConvertI64Unsigned => self.unop(|a: i64| a as u64 as f32)?,
+            F32DemoteF64 => self.unop(|a: f64| a as f32)?,
+
+            F64ConvertI32Signed => self.unop(|a: i32| a as f64)?,
+            F64ConvertI32Unsigned => self.unop(|a: i32| a as u32 as f64)?,
+            F64ConvertI64Signed => self.unop(|a: i64| a as f64)?,
+            F64ConvertI64Unsigned => self.unop(|a: i64| a as u64 as f64)?,
+            F64PromoteF32 => self.unop(|a: f32| a as f64)?,
+
+            // `Dynamic` stores the raw bytes of a value, so reinterpreting is a no-op
+            I32ReinterpretAsF32 | I64ReinterpretAsF64 | F32ReinterpretAsI32
+            | F64ReinterpretAsI64 => {}
+
+            I32Extend8Signed => self.unop(|a: i32| a as i8 as i32)?,
+            I32Extend16Signed => self.unop(|a: i32| a as i16 as i32)?,
+            I64Extend8Signed => self.unop(|a: i64| a as i8 as i64)?,
+            I64Extend16Signed => self.unop(|a: i64| a as i16 as i64)?,
+            I64Extend32Signed => self.unop(|a: i64| a as i32 as i64)?,
+
+            // `as` casts from floats to integers saturate, and convert NaN to 0
+            I32TruncSatF32Signed => self.unop(|a: f32| a as i32)?,
+            I32TruncSatF32Unsigned => self.unop(|a: f32| a as u32 as i32)?,
+            I32TruncSatF64Signed => self.unop(|a: f64| a as i32)?,
+            I32TruncSatF64Unsigned => self.unop(|a: f64| a as u32 as i32)?,
+            I64TruncSatF32Signed => self.unop(|a: f32| a as i64)?,
+            I64TruncSatF32Unsigned => self.unop(|a: f32| a as u64 as i64)?,
+            I64TruncSatF64Signed => self.unop(|a: f64| a as i64)?,
+            I64TruncSatF64Unsigned => self.unop(|a: f64| a as u64 as i64)?,
+
+            _ => return Err(Trap::UnimplementedInstruction),
+        }
+        Ok(())
+    }
 }
 
 /// Result of [`Process`]' `step` function.
 pub enum ProcessAction<'a> {
     /// No action should be taken, you can freely call `step` again.
     None,
-    /// The function finished with the given return values.
+    /// The entry function finished with the given return values. Any further call to `step` will return an error.
     Finished(Vec<Dynamic>),
     /// The wasm binary tried calling the given function. You *have* to do one of two things:
     ///
     /// - If you're using [`derive_ffi_handler`], call `embedded_wasm::FfiHandler::handle(&mut your_state, &mut process, function, args)`
     /// - If you're handling this manually, make sure to call `process::stack_push` for each return value that your function returns.
     ///
+    /// The next call to `step` will return an error if the amount of pushed values does not match the function's signature.
+    ///
     /// [`derive_ffi_handler`]: macro.derive_ffi_handler.html
     CallExtern {
         /// The function name that is being called.
@@ -165,13 +1162,104 @@ pub enum ProcessAction<'a> {
     },
 }
 
+/// An opaque handle that the host can pass to the wasm code as an `externref`, e.g. a handle to a peripheral.
+///
+/// The wasm code can not inspect the value of the handle, it can only pass it back to the host or store it in a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExternRef(pub u32);
+
+/// A typed value that is passed to the entry function of a process, see [`Wasm`]'s `spawn_with_args()` function.
+///
+/// Unlike [`Dynamic`] this knows its type, so it can be checked against the signature of the function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// An `i32` value
+    I32(i32),
+    /// An `i64` value
+    I64(i64),
+    /// An `f32` value
+    F32(f32),
+    /// An `f64` value
+    F64(f64),
+    /// A `v128` value
+    #[cfg(feature = "simd")]
+    V128([u8; 16]),
+    /// An `externref` value, `None` is a null reference
+    ExternRef(Option<ExternRef>),
+}
+
+impl Value {
+    /// The wasm type of this value.
+    pub(crate) fn val_type(&self) -> ValType {
+        match self {
+            Value::I32(_) => ValType::Num(NumType::I32),
+            Value::I64(_) => ValType::Num(NumType::I64),
+            Value::F32(_) => ValType::Num(NumType::F32),
+            Value::F64(_) => ValType::Num(NumType::F64),
+            #[cfg(feature = "simd")]
+            Value::V128(_) => ValType::V128,
+            Value::ExternRef(_) => ValType::Ref(RefType::ExternRef),
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Self::I32(i)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Self::I64(i)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(f: f32) -> Self {
+        Self::F32(f)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Self::F64(f)
+    }
+}
+
+impl From<ExternRef> for Value {
+    fn from(r: ExternRef) -> Self {
+        Self::ExternRef(Some(r))
+    }
+}
+
+impl From<Value> for Dynamic {
+    fn from(v: Value) -> Self {
+        match v {
+            Value::I32(i) => i.into(),
+            Value::I64(i) => i.into(),
+            Value::F32(f) => f.into(),
+            Value::F64(f) => f.into(),
+            #[cfg(feature = "simd")]
+            Value::V128(v) => v.into(),
+            Value::ExternRef(r) => r.into(),
+        }
+    }
+}
+
+/// The size of a [`Dynamic`] value. This is large enough to hold a `v128` when the `simd` feature is enabled.
+#[cfg(feature = "simd")]
+const DYNAMIC_SIZE: usize = 16;
+#[cfg(not(feature = "simd"))]
+const DYNAMIC_SIZE: usize = 8;
+
 /// A dynamic value type.
-#[derive(Debug, Clone)]
-pub struct Dynamic([u8; 8]);
+#[derive(Debug, Clone, Default)]
+pub struct Dynamic([u8; DYNAMIC_SIZE]);
 
 impl From<i32> for Dynamic {
     fn from(i: i32) -> Self {
-        let mut bytes = [0u8; 8];
+        let mut bytes = [0u8; DYNAMIC_SIZE];
         bytes[..4].copy_from_slice(&i.to_le_bytes());
         Self(bytes)
     }
@@ -179,15 +1267,15 @@ impl From<i32> for Dynamic {
 
 impl From<i64> for Dynamic {
     fn from(i: i64) -> Self {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&i.to_le_bytes());
+        let mut bytes = [0u8; DYNAMIC_SIZE];
+        bytes[..8].copy_from_slice(&i.to_le_bytes());
         Self(bytes)
     }
 }
 
 impl From<f32> for Dynamic {
     fn from(i: f32) -> Self {
-        let mut bytes = [0u8; 8];
+        let mut bytes = [0u8; DYNAMIC_SIZE];
         bytes[..4].copy_from_slice(&i.to_le_bytes());
         Self(bytes)
     }
@@ -195,8 +1283,8 @@ impl From<f32> for Dynamic {
 
 impl From<f64> for Dynamic {
     fn from(i: f64) -> Self {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&i.to_le_bytes());
+        let mut bytes = [0u8; DYNAMIC_SIZE];
+        bytes[..8].copy_from_slice(&i.to_le_bytes());
         Self(bytes)
     }
 }
@@ -212,11 +1300,68 @@ impl Dynamic {
     }
     /// Cast the dynamic value to an `i64`
     pub fn as_i64(&self) -> i64 {
-        i64::from_le_bytes(self.0)
+        i64::from_le_bytes(self.0[..8].try_into().unwrap())
     }
     /// Cast the dynamic value to an `f64`
     pub fn as_f64(&self) -> f64 {
-        f64::from_le_bytes(self.0)
+        f64::from_le_bytes(self.0[..8].try_into().unwrap())
+    }
+    /// Cast the dynamic value to a `v128`
+    #[cfg(feature = "simd")]
+    pub fn as_v128(&self) -> [u8; 16] {
+        self.0
+    }
+    /// Cast the dynamic value to an `externref`. Returns `None` for a null reference.
+    pub fn as_extern_ref(&self) -> Option<ExternRef> {
+        self.as_reference().map(ExternRef)
+    }
+    /// Create a reference to the function or extern with the given index, or a null reference.
+    ///
+    /// The index is stored in the first 4 bytes, and the 5th byte is set for non-null references. This makes the default value of `Dynamic` a null reference.
+    pub(crate) fn from_reference(index: Option<u32>) -> Self {
+        let mut bytes = [0u8; DYNAMIC_SIZE];
+        if let Some(index) = index {
+            bytes[..4].copy_from_slice(&index.to_le_bytes());
+            bytes[4] = 1;
+        }
+        Self(bytes)
+    }
+    /// Cast the dynamic value to a reference. Returns `None` for a null reference.
+    pub(crate) fn as_reference(&self) -> Option<u32> {
+        if self.0[4] == 0 {
+            None
+        } else {
+            Some(self.as_i32() as u32)
+        }
+    }
+}
+
+/// Conversion from a [`Dynamic`] value to a concrete type.
+trait FromDynamic {
+    fn from_dynamic(val: &Dynamic) -> Self;
+}
+
+impl FromDynamic for i32 {
+    fn from_dynamic(val: &Dynamic) -> Self {
+        val.as_i32()
+    }
+}
+
+impl FromDynamic for i64 {
+    fn from_dynamic(val: &Dynamic) -> Self {
+        val.as_i64()
+    }
+}
+
+impl FromDynamic for f32 {
+    fn from_dynamic(val: &Dynamic) -> Self {
+        val.as_f32()
+    }
+}
+
+impl FromDynamic for f64 {
+    fn from_dynamic(val: &Dynamic) -> Self {
+        val.as_f64()
     }
 }
 
@@ -224,6 +1369,7 @@ impl From<ValType> for Dynamic {
     fn from(t: ValType) -> Self {
         match t {
             ValType::Num(num_type) => num_type.into(),
+            ValType::V128 => Self::default(),
             ValType::Ref(ref_type) => ref_type.into(),
         }
     }
@@ -232,39 +1378,70 @@ impl From<ValType> for Dynamic {
 impl From<NumType> for Dynamic {
     fn from(n: NumType) -> Self {
         match n {
-            NumType::I32 | NumType::I64 => Self([0u8; 8]),
+            NumType::I32 | NumType::I64 => Self::default(),
             NumType::F32 => 0.0f32.into(),
             NumType::F64 => 0.0f64.into(),
         }
     }
 }
 
+#[cfg(feature = "simd")]
+impl From<[u8; 16]> for Dynamic {
+    fn from(v: [u8; 16]) -> Self {
+        Self(v)
+    }
+}
+
+impl From<ExternRef> for Dynamic {
+    fn from(r: ExternRef) -> Self {
+        Self::from_reference(Some(r.0))
+    }
+}
+
+impl From<Option<ExternRef>> for Dynamic {
+    fn from(r: Option<ExternRef>) -> Self {
+        Self::from_reference(r.map(|r| r.0))
+    }
+}
+
 impl From<RefType> for Dynamic {
     fn from(r: RefType) -> Self {
         match r {
-            RefType::FuncRef => todo!(),
-            RefType::ExternRef => todo!(),
+            RefType::FuncRef | RefType::ExternRef => Self::from_reference(None),
         }
     }
 }
 
-struct ProgramCounter {
+struct ProgramCounter<'a> {
     pub func: FuncIdx,
-    pub idx: Vec<usize>,
+    pub body: BodyRef<'a>,
+    /// The offset of the current instruction in the body of the function.
+    pub pc: usize,
     pub locals: Vec<Dynamic>,
+    /// The height of the value stack when this frame was entered, excluding its arguments.
+    pub stack_height: usize,
 }
 
-impl ProgramCounter {
-    #[allow(clippy::vec_init_then_push)]
-    pub fn new(func: FuncIdx, l: &[(u32, ValType)]) -> Self {
-        let mut locals = Vec::with_capacity(l.iter().map(|(count, _)| *count as usize).sum());
+impl<'a> ProgramCounter<'a> {
+    pub fn new(
+        func: FuncIdx,
+        body: BodyRef<'a>,
+        mut locals: Vec<Dynamic>,
+        stack_height: usize,
+    ) -> Self {
+        let l = &body.locals;
+        locals.reserve(l.iter().map(|(count, _)| *count as usize).sum());
         for (count, local) in l.iter().copied() {
             for _ in 0..count {
                 locals.push(local.into())
             }
         }
-        let mut idx = Vec::new();
-        idx.push(0);
-        Self { func, idx, locals }
+        Self {
+            func,
+            body,
+            pc: 0,
+            locals,
+            stack_height,
+        }
     }
 }
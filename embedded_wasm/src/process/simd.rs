@@ -0,0 +1,826 @@
+//! Execution of the SIMD (`v128`) instructions.
+//!
+//! All lane operations are implemented with scalar code, so they behave the same on every target.
+
+use super::Process;
+use crate::{
+    float::Float,
+    instruction::{MemArg, Signedness, VectorInstruction},
+    Trap,
+};
+
+type V128 = [u8; 16];
+
+/// A single lane of a [`V128`].
+trait Lane: Copy {
+    const SIZE: usize;
+    fn read(bytes: &[u8]) -> Self;
+    fn write(self, bytes: &mut [u8]);
+}
+
+macro_rules! impl_lane {
+    ($($ty:ty),*) => {
+        $(
+            impl Lane for $ty {
+                const SIZE: usize = core::mem::size_of::<$ty>();
+                fn read(bytes: &[u8]) -> Self {
+                    <$ty>::from_le_bytes(bytes[..Self::SIZE].try_into().unwrap())
+                }
+                fn write(self, bytes: &mut [u8]) {
+                    bytes[..Self::SIZE].copy_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_lane!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+fn lane<T: Lane>(v: &V128, idx: usize) -> T {
+    T::read(&v[idx * T::SIZE..])
+}
+
+fn set_lane<T: Lane>(v: &mut V128, idx: usize, value: T) {
+    value.write(&mut v[idx * T::SIZE..]);
+}
+
+fn lane_count<T: Lane>() -> usize {
+    16 / T::SIZE
+}
+
+fn splat<T: Lane>(value: T) -> V128 {
+    let mut result = [0u8; 16];
+    for idx in 0..lane_count::<T>() {
+        set_lane(&mut result, idx, value);
+    }
+    result
+}
+
+/// Create a vector from the given bytes, setting the remaining bytes to zero.
+fn zero_extend(bytes: &[u8]) -> V128 {
+    let mut result = [0u8; 16];
+    result[..bytes.len()].copy_from_slice(bytes);
+    result
+}
+
+fn map<T: Lane>(v: V128, f: impl Fn(T) -> T) -> V128 {
+    let mut result = [0u8; 16];
+    for idx in 0..lane_count::<T>() {
+        set_lane(&mut result, idx, f(lane(&v, idx)));
+    }
+    result
+}
+
+fn zip<T: Lane>(a: V128, b: V128, f: impl Fn(T, T) -> T) -> V128 {
+    let mut result = [0u8; 16];
+    for idx in 0..lane_count::<T>() {
+        set_lane(&mut result, idx, f(lane(&a, idx), lane(&b, idx)));
+    }
+    result
+}
+
+/// Compare every lane, setting all bits of the resulting lane if `f` returns `true`.
+fn compare<T: Lane>(a: V128, b: V128, f: impl Fn(T, T) -> bool) -> V128 {
+    let mut result = [0u8; 16];
+    for idx in 0..lane_count::<T>() {
+        if f(lane(&a, idx), lane(&b, idx)) {
+            result[idx * T::SIZE..(idx + 1) * T::SIZE].fill(0xFF);
+        }
+    }
+    result
+}
+
+/// Convert `count` lanes of `v`, starting at lane `first`, to lanes of a different type. The remaining lanes are zero.
+fn convert<T: Lane, R: Lane>(v: V128, first: usize, count: usize, f: impl Fn(T) -> R) -> V128 {
+    let mut result = [0u8; 16];
+    for idx in 0..count {
+        set_lane(&mut result, idx, f(lane(&v, first + idx)));
+    }
+    result
+}
+
+/// Convert the lanes of `a` followed by the lanes of `b` to lanes of half the size.
+fn narrow<T: Lane, R: Lane>(a: V128, b: V128, f: impl Fn(T) -> R) -> V128 {
+    let count = lane_count::<T>();
+    let mut result = [0u8; 16];
+    for idx in 0..count {
+        set_lane(&mut result, idx, f(lane(&a, idx)));
+        set_lane(&mut result, count + idx, f(lane(&b, idx)));
+    }
+    result
+}
+
+/// Combine the lanes of `a` and `b`, starting at lane `first`, into lanes of twice the size.
+fn widen<T: Lane, R: Lane>(a: V128, b: V128, first: usize, f: impl Fn(T, T) -> R) -> V128 {
+    let mut result = [0u8; 16];
+    for idx in 0..lane_count::<R>() {
+        let value = f(lane(&a, first + idx), lane(&b, first + idx));
+        set_lane(&mut result, idx, value);
+    }
+    result
+}
+
+/// Combine every pair of adjacent lanes into a lane of twice the size.
+fn pairwise<T: Lane, R: Lane>(v: V128, f: impl Fn(T, T) -> R) -> V128 {
+    let mut result = [0u8; 16];
+    for idx in 0..lane_count::<R>() {
+        set_lane(
+            &mut result,
+            idx,
+            f(lane(&v, idx * 2), lane(&v, idx * 2 + 1)),
+        );
+    }
+    result
+}
+
+/// Returns `true` if every lane is non-zero.
+fn all_true<T: Lane + PartialEq + Default>(v: V128) -> bool {
+    (0..lane_count::<T>()).all(|idx| lane::<T>(&v, idx) != T::default())
+}
+
+/// Collect the highest bit of every lane.
+fn bitmask<T: Lane>(v: V128) -> i32 {
+    (0..lane_count::<T>()).fold(0, |mask, idx| {
+        let high_byte = v[(idx + 1) * T::SIZE - 1];
+        mask | (((high_byte >> 7) as i32) << idx)
+    })
+}
+
+/// Pick the signed or unsigned version of an operation.
+macro_rules! signed {
+    ($signedness:expr, $signed:expr, $unsigned:expr) => {
+        match $signedness {
+            Signedness::Signed => $signed,
+            Signedness::Unsigned => $unsigned,
+        }
+    };
+}
+
+impl<'a> Process<'a> {
+    pub(super) fn execute_vector(&mut self, instruction: &VectorInstruction) -> Result<(), Trap> {
+        use VectorInstruction::*;
+        match *instruction {
+            V128Load(memarg) => {
+                let bytes = self.load_bytes::<16>(&memarg)?;
+                self.push_v128(bytes);
+            }
+            V128Load8x8(memarg, signedness) => {
+                let bytes = zero_extend(&self.load_bytes::<8>(&memarg)?);
+                self.push_v128(signed!(
+                    signedness,
+                    convert(bytes, 0, 8, |a: i8| a as i16),
+                    convert(bytes, 0, 8, |a: u8| a as u16)
+                ));
+            }
+            V128Load16x4(memarg, signedness) => {
+                let bytes = zero_extend(&self.load_bytes::<8>(&memarg)?);
+                self.push_v128(signed!(
+                    signedness,
+                    convert(bytes, 0, 4, |a: i16| a as i32),
+                    convert(bytes, 0, 4, |a: u16| a as u32)
+                ));
+            }
+            V128Load32x2(memarg, signedness) => {
+                let bytes = zero_extend(&self.load_bytes::<8>(&memarg)?);
+                self.push_v128(signed!(
+                    signedness,
+                    convert(bytes, 0, 2, |a: i32| a as i64),
+                    convert(bytes, 0, 2, |a: u32| a as u64)
+                ));
+            }
+            V128Load8Splat(memarg) => {
+                let value = u8::from_le_bytes(self.load_bytes(&memarg)?);
+                self.push_v128(splat(value));
+            }
+            V128Load16Splat(memarg) => {
+                let value = u16::from_le_bytes(self.load_bytes(&memarg)?);
+                self.push_v128(splat(value));
+            }
+            V128Load32Splat(memarg) => {
+                let value = u32::from_le_bytes(self.load_bytes(&memarg)?);
+                self.push_v128(splat(value));
+            }
+            V128Load64Splat(memarg) => {
+                let value = u64::from_le_bytes(self.load_bytes(&memarg)?);
+                self.push_v128(splat(value));
+            }
+            V128Load32Zero(memarg) => {
+                let bytes = self.load_bytes::<4>(&memarg)?;
+                self.push_v128(zero_extend(&bytes));
+            }
+            V128Load64Zero(memarg) => {
+                let bytes = self.load_bytes::<8>(&memarg)?;
+                self.push_v128(zero_extend(&bytes));
+            }
+            V128Store(memarg) => {
+                let value = self.pop_v128()?;
+                self.store_bytes(&memarg, &value)?;
+            }
+            V128Load8Lane(memarg, idx) => self.load_lane::<1>(&memarg, idx.0)?,
+            V128Load16Lane(memarg, idx) => self.load_lane::<2>(&memarg, idx.0)?,
+            V128Load32Lane(memarg, idx) => self.load_lane::<4>(&memarg, idx.0)?,
+            V128Load64Lane(memarg, idx) => self.load_lane::<8>(&memarg, idx.0)?,
+            V128Store8Lane(memarg, idx) => self.store_lane::<1>(&memarg, idx.0)?,
+            V128Store16Lane(memarg, idx) => self.store_lane::<2>(&memarg, idx.0)?,
+            V128Store32Lane(memarg, idx) => self.store_lane::<4>(&memarg, idx.0)?,
+            V128Store64Lane(memarg, idx) => self.store_lane::<8>(&memarg, idx.0)?,
+            V128Const(bytes) => self.push_v128(bytes),
+            I8x16Shuffle(lanes) => {
+                let b = self.pop_v128()?;
+                let a = self.pop_v128()?;
+                let mut result = [0u8; 16];
+                for (result, idx) in result.iter_mut().zip(lanes) {
+                    let idx = idx.0 as usize;
+                    *result = if idx < 16 { a[idx] } else { b[idx % 16] };
+                }
+                self.push_v128(result);
+            }
+            I8x16ExtractLane(idx, signedness) => {
+                let v = self.pop_v128()?;
+                let value = signed!(
+                    signedness,
+                    lane::<i8>(&v, idx.0 as usize) as i32,
+                    lane::<u8>(&v, idx.0 as usize) as i32
+                );
+                self.stack_push(value);
+            }
+            I16x8ExtractLane(idx, signedness) => {
+                let v = self.pop_v128()?;
+                let value = signed!(
+                    signedness,
+                    lane::<i16>(&v, idx.0 as usize) as i32,
+                    lane::<u16>(&v, idx.0 as usize) as i32
+                );
+                self.stack_push(value);
+            }
+            I32x4ExtractLane(idx) => {
+                let v = self.pop_v128()?;
+                self.stack_push(lane::<i32>(&v, idx.0 as usize));
+            }
+            I64x2ExtractLane(idx) => {
+                let v = self.pop_v128()?;
+                self.stack_push(lane::<i64>(&v, idx.0 as usize));
+            }
+            F32x4ExtractLane(idx) => {
+                let v = self.pop_v128()?;
+                self.stack_push(lane::<f32>(&v, idx.0 as usize));
+            }
+            F64x2ExtractLane(idx) => {
+                let v = self.pop_v128()?;
+                self.stack_push(lane::<f64>(&v, idx.0 as usize));
+            }
+            I8x16ReplaceLane(idx) => {
+                let value = self.stack_pop()?.as_i32() as u8;
+                self.replace_lane(idx.0, value)?;
+            }
+            I16x8ReplaceLane(idx) => {
+                let value = self.stack_pop()?.as_i32() as u16;
+                self.replace_lane(idx.0, value)?;
+            }
+            I32x4ReplaceLane(idx) => {
+                let value = self.stack_pop()?.as_i32();
+                self.replace_lane(idx.0, value)?;
+            }
+            I64x2ReplaceLane(idx) => {
+                let value = self.stack_pop()?.as_i64();
+                self.replace_lane(idx.0, value)?;
+            }
+            F32x4ReplaceLane(idx) => {
+                let value = self.stack_pop()?.as_f32();
+                self.replace_lane(idx.0, value)?;
+            }
+            F64x2ReplaceLane(idx) => {
+                let value = self.stack_pop()?.as_f64();
+                self.replace_lane(idx.0, value)?;
+            }
+
+            I8x16Swizzle => {
+                let s = self.pop_v128()?;
+                let a = self.pop_v128()?;
+                self.push_v128(s.map(|idx| a.get(idx as usize).copied().unwrap_or(0)));
+            }
+            I8x16Splat => {
+                let value = self.stack_pop()?.as_i32() as u8;
+                self.push_v128(splat(value));
+            }
+            I16x8Splat => {
+                let value = self.stack_pop()?.as_i32() as u16;
+                self.push_v128(splat(value));
+            }
+            I32x4Splat => {
+                let value = self.stack_pop()?.as_i32();
+                self.push_v128(splat(value));
+            }
+            I64x2Splat => {
+                let value = self.stack_pop()?.as_i64();
+                self.push_v128(splat(value));
+            }
+            F32x4Splat => {
+                let value = self.stack_pop()?.as_f32();
+                self.push_v128(splat(value));
+            }
+            F64x2Splat => {
+                let value = self.stack_pop()?.as_f64();
+                self.push_v128(splat(value));
+            }
+
+            I8x16Equal => self.vcompare(|a: u8, b| a == b)?,
+            I8x16NotEqual => self.vcompare(|a: u8, b| a != b)?,
+            I8x16LessThan(s) => signed!(
+                s,
+                self.vcompare(|a: i8, b| a < b),
+                self.vcompare(|a: u8, b| a < b)
+            )?,
+            I8x16GreaterThan(s) => signed!(
+                s,
+                self.vcompare(|a: i8, b| a > b),
+                self.vcompare(|a: u8, b| a > b)
+            )?,
+            I8x16LessOrEqualTo(s) => signed!(
+                s,
+                self.vcompare(|a: i8, b| a <= b),
+                self.vcompare(|a: u8, b| a <= b)
+            )?,
+            I8x16GreaterOrEqualTo(s) => signed!(
+                s,
+                self.vcompare(|a: i8, b| a >= b),
+                self.vcompare(|a: u8, b| a >= b)
+            )?,
+
+            I16x8Equal => self.vcompare(|a: u16, b| a == b)?,
+            I16x8NotEqual => self.vcompare(|a: u16, b| a != b)?,
+            I16x8LessThan(s) => signed!(
+                s,
+                self.vcompare(|a: i16, b| a < b),
+                self.vcompare(|a: u16, b| a < b)
+            )?,
+            I16x8GreaterThan(s) => signed!(
+                s,
+                self.vcompare(|a: i16, b| a > b),
+                self.vcompare(|a: u16, b| a > b)
+            )?,
+            I16x8LessOrEqualTo(s) => signed!(
+                s,
+                self.vcompare(|a: i16, b| a <= b),
+                self.vcompare(|a: u16, b| a <= b)
+            )?,
+            I16x8GreaterOrEqualTo(s) => signed!(
+                s,
+                self.vcompare(|a: i16, b| a >= b),
+                self.vcompare(|a: u16, b| a >= b)
+            )?,
+
+            I32x4Equal => self.vcompare(|a: u32, b| a == b)?,
+            I32x4NotEqual => self.vcompare(|a: u32, b| a != b)?,
+            I32x4LessThan(s) => signed!(
+                s,
+                self.vcompare(|a: i32, b| a < b),
+                self.vcompare(|a: u32, b| a < b)
+            )?,
+            I32x4GreaterThan(s) => signed!(
+                s,
+                self.vcompare(|a: i32, b| a > b),
+                self.vcompare(|a: u32, b| a > b)
+            )?,
+            I32x4LessOrEqualTo(s) => signed!(
+                s,
+                self.vcompare(|a: i32, b| a <= b),
+                self.vcompare(|a: u32, b| a <= b)
+            )?,
+            I32x4GreaterOrEqualTo(s) => signed!(
+                s,
+                self.vcompare(|a: i32, b| a >= b),
+                self.vcompare(|a: u32, b| a >= b)
+            )?,
+
+            I64x2Equal => self.vcompare(|a: i64, b| a == b)?,
+            I64x2NotEqual => self.vcompare(|a: i64, b| a != b)?,
+            I64x2LessThan => self.vcompare(|a: i64, b| a < b)?,
+            I64x2GreaterThan => self.vcompare(|a: i64, b| a > b)?,
+            I64x2LessOrEqualTo => self.vcompare(|a: i64, b| a <= b)?,
+            I64x2GreaterOrEqualTo => self.vcompare(|a: i64, b| a >= b)?,
+
+            F32x4Equal => self.vcompare(|a: f32, b| a == b)?,
+            F32x4NotEqual => self.vcompare(|a: f32, b| a != b)?,
+            F32x4LessThan => self.vcompare(|a: f32, b| a < b)?,
+            F32x4GreaterThan => self.vcompare(|a: f32, b| a > b)?,
+            F32x4LessOrEqualTo => self.vcompare(|a: f32, b| a <= b)?,
+            F32x4GreaterOrEqualTo => self.vcompare(|a: f32, b| a >= b)?,
+
+            F64x2Equal => self.vcompare(|a: f64, b| a == b)?,
+            F64x2NotEqual => self.vcompare(|a: f64, b| a != b)?,
+            F64x2LessThan => self.vcompare(|a: f64, b| a < b)?,
+            F64x2GreaterThan => self.vcompare(|a: f64, b| a > b)?,
+            F64x2LessOrEqualTo => self.vcompare(|a: f64, b| a <= b)?,
+            F64x2GreaterOrEqualTo => self.vcompare(|a: f64, b| a >= b)?,
+
+            V128Not => self.vunop(|a: u64| !a)?,
+            V128And => self.vbinop(|a: u64, b| a & b)?,
+            V128AndNot => self.vbinop(|a: u64, b| a & !b)?,
+            V128Or => self.vbinop(|a: u64, b| a | b)?,
+            V128Xor => self.vbinop(|a: u64, b| a ^ b)?,
+            V128BitSelect => {
+                let c = self.pop_v128()?;
+                let b = self.pop_v128()?;
+                let a = self.pop_v128()?;
+                let result = core::array::from_fn(|idx| (a[idx] & c[idx]) | (b[idx] & !c[idx]));
+                self.push_v128(result);
+            }
+            V128AnyTrue => {
+                let v = self.pop_v128()?;
+                self.stack_push(v.iter().any(|b| *b != 0) as i32);
+            }
+
+            I8x16Abs => self.vunop(|a: i8| a.wrapping_abs())?,
+            I8x16Neg => self.vunop(|a: i8| a.wrapping_neg())?,
+            I8x16PopCnt => self.vunop(|a: u8| a.count_ones() as u8)?,
+            I8x16AllTrue => self.all_true::<u8>()?,
+            I8x16Bitmask => self.bitmask::<u8>()?,
+            I8x16NarrowI16x8(s) => {
+                let b = self.pop_v128()?;
+                let a = self.pop_v128()?;
+                self.push_v128(signed!(
+                    s,
+                    narrow(a, b, |a: i16| a.clamp(i8::MIN as i16, i8::MAX as i16) as i8),
+                    narrow(a, b, |a: i16| a.clamp(0, u8::MAX as i16) as u8)
+                ));
+            }
+            I8x16ShiftLeft => self.vshift(|a: u8, s| a.wrapping_shl(s))?,
+            I8x16ShiftRight(s) => signed!(
+                s,
+                self.vshift(|a: i8, s| a.wrapping_shr(s)),
+                self.vshift(|a: u8, s| a.wrapping_shr(s))
+            )?,
+            I8x16Add => self.vbinop(|a: u8, b| a.wrapping_add(b))?,
+            I8x16AddSaturating(s) => signed!(
+                s,
+                self.vbinop(|a: i8, b| a.saturating_add(b)),
+                self.vbinop(|a: u8, b| a.saturating_add(b))
+            )?,
+            I8x16Sub => self.vbinop(|a: u8, b| a.wrapping_sub(b))?,
+            I8x16SubSaturating(s) => signed!(
+                s,
+                self.vbinop(|a: i8, b| a.saturating_sub(b)),
+                self.vbinop(|a: u8, b| a.saturating_sub(b))
+            )?,
+            I8x16Min(s) => signed!(
+                s,
+                self.vbinop(|a: i8, b| a.min(b)),
+                self.vbinop(|a: u8, b| a.min(b))
+            )?,
+            I8x16Max(s) => signed!(
+                s,
+                self.vbinop(|a: i8, b| a.max(b)),
+                self.vbinop(|a: u8, b| a.max(b))
+            )?,
+            I8x16Average => self.vbinop(|a: u8, b| (a as u16 + b as u16).div_ceil(2) as u8)?,
+
+            I16x8ExtAddPairWiseI8x16(s) => {
+                let v = self.pop_v128()?;
+                self.push_v128(signed!(
+                    s,
+                    pairwise(v, |a: i8, b: i8| a as i16 + b as i16),
+                    pairwise(v, |a: u8, b: u8| a as u16 + b as u16)
+                ));
+            }
+            I16x8Abs => self.vunop(|a: i16| a.wrapping_abs())?,
+            I16x8Neg => self.vunop(|a: i16| a.wrapping_neg())?,
+            I16x8Q16MulrSat => self.vbinop(|a: i16, b| {
+                let product = (a as i32 * b as i32 + 0x4000) >> 15;
+                product.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+            })?,
+            I16x8AllTrue => self.all_true::<u16>()?,
+            I16x8Bitmask => self.bitmask::<u16>()?,
+            I16x8NarrowI32x4(s) => {
+                let b = self.pop_v128()?;
+                let a = self.pop_v128()?;
+                self.push_v128(signed!(
+                    s,
+                    narrow(a, b, |a: i32| a.clamp(i16::MIN as i32, i16::MAX as i32)
+                        as i16),
+                    narrow(a, b, |a: i32| a.clamp(0, u16::MAX as i32) as u16)
+                ));
+            }
+            I16x8ExtendLowI8x16(s) => signed!(
+                s,
+                self.vextend(0, |a: i8| a as i16),
+                self.vextend(0, |a: u8| a as u16)
+            )?,
+            I16x8ExtendHighI8x16(s) => signed!(
+                s,
+                self.vextend(8, |a: i8| a as i16),
+                self.vextend(8, |a: u8| a as u16)
+            )?,
+            I16x8ShiftLeft => self.vshift(|a: u16, s| a.wrapping_shl(s))?,
+            I16x8ShiftRight(s) => signed!(
+                s,
+                self.vshift(|a: i16, s| a.wrapping_shr(s)),
+                self.vshift(|a: u16, s| a.wrapping_shr(s))
+            )?,
+            I16x8Add => self.vbinop(|a: u16, b| a.wrapping_add(b))?,
+            I16x8AddSaturating(s) => signed!(
+                s,
+                self.vbinop(|a: i16, b| a.saturating_add(b)),
+                self.vbinop(|a: u16, b| a.saturating_add(b))
+            )?,
+            I16x8Sub => self.vbinop(|a: u16, b| a.wrapping_sub(b))?,
+            I16x8SubSaturating(s) => signed!(
+                s,
+                self.vbinop(|a: i16, b| a.saturating_sub(b)),
+                self.vbinop(|a: u16, b| a.saturating_sub(b))
+            )?,
+            I16x8Mul => self.vbinop(|a: u16, b| a.wrapping_mul(b))?,
+            I16x8Min(s) => signed!(
+                s,
+                self.vbinop(|a: i16, b| a.min(b)),
+                self.vbinop(|a: u16, b| a.min(b))
+            )?,
+            I16x8Max(s) => signed!(
+                s,
+                self.vbinop(|a: i16, b| a.max(b)),
+                self.vbinop(|a: u16, b| a.max(b))
+            )?,
+            I16x8Average => self.vbinop(|a: u16, b| (a as u32 + b as u32).div_ceil(2) as u16)?,
+            I16x8ExtMulLowI8x16(s) => signed!(
+                s,
+                self.vext_mul(0, |a: i8, b: i8| a as i16 * b as i16),
+                self.vext_mul(0, |a: u8, b: u8| a as u16 * b as u16)
+            )?,
+            I16x8ExtMulHighI8x16(s) => signed!(
+                s,
+                self.vext_mul(8, |a: i8, b: i8| a as i16 * b as i16),
+                self.vext_mul(8, |a: u8, b: u8| a as u16 * b as u16)
+            )?,
+
+            I32x4ExtAddPairwiseI16x8(s) => {
+                let v = self.pop_v128()?;
+                self.push_v128(signed!(
+                    s,
+                    pairwise(v, |a: i16, b: i16| a as i32 + b as i32),
+                    pairwise(v, |a: u16, b: u16| a as u32 + b as u32)
+                ));
+            }
+            I32x4Abs => self.vunop(|a: i32| a.wrapping_abs())?,
+            I32x4Neg => self.vunop(|a: i32| a.wrapping_neg())?,
+            I32x4AllTrue => self.all_true::<u32>()?,
+            I32x4Bitmask => self.bitmask::<u32>()?,
+            I32x4ExtendLowI16x8(s) => signed!(
+                s,
+                self.vextend(0, |a: i16| a as i32),
+                self.vextend(0, |a: u16| a as u32)
+            )?,
+            I32x4ExtendHighI16x8(s) => signed!(
+                s,
+                self.vextend(4, |a: i16| a as i32),
+                self.vextend(4, |a: u16| a as u32)
+            )?,
+            I32x4ShiftLeft => self.vshift(|a: u32, s| a.wrapping_shl(s))?,
+            I32x4ShiftRight(s) => signed!(
+                s,
+                self.vshift(|a: i32, s| a.wrapping_shr(s)),
+                self.vshift(|a: u32, s| a.wrapping_shr(s))
+            )?,
+            I32x4Add => self.vbinop(|a: u32, b| a.wrapping_add(b))?,
+            I32x4Sub => self.vbinop(|a: u32, b| a.wrapping_sub(b))?,
+            I32x4Mul => self.vbinop(|a: u32, b| a.wrapping_mul(b))?,
+            I32x4Min(s) => signed!(
+                s,
+                self.vbinop(|a: i32, b| a.min(b)),
+                self.vbinop(|a: u32, b| a.min(b))
+            )?,
+            I32x4Max(s) => signed!(
+                s,
+                self.vbinop(|a: i32, b| a.max(b)),
+                self.vbinop(|a: u32, b| a.max(b))
+            )?,
+            I32x4DotI16x8 => {
+                let b = self.pop_v128()?;
+                let a = self.pop_v128()?;
+                let mut result = [0u8; 16];
+                for idx in 0..4 {
+                    let product = |idx| lane::<i16>(&a, idx) as i32 * lane::<i16>(&b, idx) as i32;
+                    set_lane(
+                        &mut result,
+                        idx,
+                        product(idx * 2).wrapping_add(product(idx * 2 + 1)),
+                    );
+                }
+                self.push_v128(result);
+            }
+            I32x4ExtMulLowI16x8(s) => signed!(
+                s,
+                self.vext_mul(0, |a: i16, b: i16| a as i32 * b as i32),
+                self.vext_mul(0, |a: u16, b: u16| a as u32 * b as u32)
+            )?,
+            I32x4ExtMulHighI16x8(s) => signed!(
+                s,
+                self.vext_mul(4, |a: i16, b: i16| a as i32 * b as i32),
+                self.vext_mul(4, |a: u16, b: u16| a as u32 * b as u32)
+            )?,
+
+            I64x2Abs => self.vunop(|a: i64| a.wrapping_abs())?,
+            I64x2Neg => self.vunop(|a: i64| a.wrapping_neg())?,
+            I64x2AllTrue => self.all_true::<u64>()?,
+            I64x2Bitmask => self.bitmask::<u64>()?,
+            I64x2ExtendLowI32x4(s) => signed!(
+                s,
+                self.vextend(0, |a: i32| a as i64),
+                self.vextend(0, |a: u32| a as u64)
+            )?,
+            I64x2ExtendHighI32x4(s) => signed!(
+                s,
+                self.vextend(2, |a: i32| a as i64),
+                self.vextend(2, |a: u32| a as u64)
+            )?,
+            I64x2ShiftLeft => self.vshift(|a: u64, s| a.wrapping_shl(s))?,
+            I64x2ShiftRight(s) => signed!(
+                s,
+                self.vshift(|a: i64, s| a.wrapping_shr(s)),
+                self.vshift(|a: u64, s| a.wrapping_shr(s))
+            )?,
+            I64x2Add => self.vbinop(|a: u64, b| a.wrapping_add(b))?,
+            I64x2Sub => self.vbinop(|a: u64, b| a.wrapping_sub(b))?,
+            I64x2Mul => self.vbinop(|a: u64, b| a.wrapping_mul(b))?,
+            I64x2ExtMulLowI32x4(s) => signed!(
+                s,
+                self.vext_mul(0, |a: i32, b: i32| a as i64 * b as i64),
+                self.vext_mul(0, |a: u32, b: u32| a as u64 * b as u64)
+            )?,
+            I64x2ExtMulHighI32x4(s) => signed!(
+                s,
+                self.vext_mul(2, |a: i32, b: i32| a as i64 * b as i64),
+                self.vext_mul(2, |a: u32, b: u32| a as u64 * b as u64)
+            )?,
+
+            F32x4Ceil => self.vunop(f32::wasm_ceil)?,
+            F32x4Floor => self.vunop(f32::wasm_floor)?,
+            F32x4Trunc => self.vunop(f32::wasm_trunc)?,
+            F32x4Nearest => self.vunop(f32::wasm_nearest)?,
+            F32x4Abs => self.vunop(f32::wasm_abs)?,
+            F32x4Neg => self.vunop(f32::wasm_neg)?,
+            F32x4Sqrt => self.vunop(f32::wasm_sqrt)?,
+            F32x4Add => self.vbinop(|a: f32, b| a + b)?,
+            F32x4Sub => self.vbinop(|a: f32, b| a - b)?,
+            F32x4Mul => self.vbinop(|a: f32, b| a * b)?,
+            F32x4Div => self.vbinop(|a: f32, b| a / b)?,
+            F32x4Min => self.vbinop(f32::wasm_min)?,
+            F32x4Max => self.vbinop(f32::wasm_max)?,
+            F32x4PMin => self.vbinop(|a: f32, b| if b < a { b } else { a })?,
+            F32x4PMax => self.vbinop(|a: f32, b| if a < b { b } else { a })?,
+
+            F64x2Ceil => self.vunop(f64::wasm_ceil)?,
+            F64x2Floor => self.vunop(f64::wasm_floor)?,
+            F64x2Trunc => self.vunop(f64::wasm_trunc)?,
+            F64x2Nearest => self.vunop(f64::wasm_nearest)?,
+            F64x2Abs => self.vunop(f64::wasm_abs)?,
+            F64x2Neg => self.vunop(f64::wasm_neg)?,
+            F64x2Sqrt => self.vunop(f64::wasm_sqrt)?,
+            F64x2Add => self.vbinop(|a: f64, b| a + b)?,
+            F64x2Sub => self.vbinop(|a: f64, b| a - b)?,
+            F64x2Mul => self.vbinop(|a: f64, b| a * b)?,
+            F64x2Div => self.vbinop(|a: f64, b| a / b)?,
+            F64x2Min => self.vbinop(f64::wasm_min)?,
+            F64x2Max => self.vbinop(f64::wasm_max)?,
+            F64x2PMin => self.vbinop(|a: f64, b| if b < a { b } else { a })?,
+            F64x2PMax => self.vbinop(|a: f64, b| if a < b { b } else { a })?,
+
+            // `as` casts from floats to integers saturate, and convert NaN to 0
+            I32x4TruncSatF32x4(s) => signed!(
+                s,
+                self.vconvert(4, |a: f32| a as i32),
+                self.vconvert(4, |a: f32| a as u32)
+            )?,
+            F32x4ConvertI32x4(s) => signed!(
+                s,
+                self.vconvert(4, |a: i32| a as f32),
+                self.vconvert(4, |a: u32| a as f32)
+            )?,
+            I32x4TruncSatF64x2Zero(s) => signed!(
+                s,
+                self.vconvert(2, |a: f64| a as i32),
+                self.vconvert(2, |a: f64| a as u32)
+            )?,
+            F64x2ConvertLowI32x4(s) => signed!(
+                s,
+                self.vconvert(2, |a: i32| a as f64),
+                self.vconvert(2, |a: u32| a as f64)
+            )?,
+            F32x4DemoteF64x2Zero => self.vconvert(2, |a: f64| a as f32)?,
+            F64x2PromoteLowF32x4 => self.vconvert(2, |a: f32| a as f64)?,
+        }
+        Ok(())
+    }
+
+    fn pop_v128(&mut self) -> Result<V128, Trap> {
+        Ok(self.stack_pop()?.as_v128())
+    }
+
+    fn push_v128(&mut self, v: V128) {
+        self.stack_push(v);
+    }
+
+    /// Pop an address from the stack, and load `N` bytes from memory.
+    fn load_bytes<const N: usize>(&mut self, memarg: &MemArg) -> Result<[u8; N], Trap> {
+        let range = self.memory_range(memarg, N)?;
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.memory[range]);
+        Ok(bytes)
+    }
+
+    /// Pop an address from the stack, and store `bytes` in memory.
+    fn store_bytes(&mut self, memarg: &MemArg, bytes: &[u8]) -> Result<(), Trap> {
+        let range = self.memory_range(memarg, bytes.len())?;
+        self.memory[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Pop a vector and an address from the stack, and replace lane `idx` of the vector with `N` bytes from memory.
+    fn load_lane<const N: usize>(&mut self, memarg: &MemArg, idx: u8) -> Result<(), Trap> {
+        let mut v = self.pop_v128()?;
+        let bytes = self.load_bytes::<N>(memarg)?;
+        let start = idx as usize * N;
+        v[start..start + N].copy_from_slice(&bytes);
+        self.push_v128(v);
+        Ok(())
+    }
+
+    /// Pop a vector and an address from the stack, and store lane `idx` of the vector in memory.
+    fn store_lane<const N: usize>(&mut self, memarg: &MemArg, idx: u8) -> Result<(), Trap> {
+        let v = self.pop_v128()?;
+        let start = idx as usize * N;
+        self.store_bytes(memarg, &v[start..start + N])
+    }
+
+    fn replace_lane<T: Lane>(&mut self, idx: u8, value: T) -> Result<(), Trap> {
+        let mut v = self.pop_v128()?;
+        set_lane(&mut v, idx as usize, value);
+        self.push_v128(v);
+        Ok(())
+    }
+
+    fn vunop<T: Lane>(&mut self, f: impl Fn(T) -> T) -> Result<(), Trap> {
+        let a = self.pop_v128()?;
+        self.push_v128(map(a, f));
+        Ok(())
+    }
+
+    fn vbinop<T: Lane>(&mut self, f: impl Fn(T, T) -> T) -> Result<(), Trap> {
+        let b = self.pop_v128()?;
+        let a = self.pop_v128()?;
+        self.push_v128(zip(a, b, f));
+        Ok(())
+    }
+
+    fn vcompare<T: Lane>(&mut self, f: impl Fn(T, T) -> bool) -> Result<(), Trap> {
+        let b = self.pop_v128()?;
+        let a = self.pop_v128()?;
+        self.push_v128(compare(a, b, f));
+        Ok(())
+    }
+
+    /// Pop a shift amount and a vector, and shift every lane by the amount modulo the lane width.
+    fn vshift<T: Lane>(&mut self, f: impl Fn(T, u32) -> T) -> Result<(), Trap> {
+        let amount = self.stack_pop()?.as_i32() as u32;
+        let a = self.pop_v128()?;
+        self.push_v128(map(a, |lane| f(lane, amount)));
+        Ok(())
+    }
+
+    /// Extend half of the lanes, starting at lane `first`, to lanes of twice the size.
+    fn vextend<T: Lane, R: Lane>(&mut self, first: usize, f: impl Fn(T) -> R) -> Result<(), Trap> {
+        let a = self.pop_v128()?;
+        self.push_v128(convert(a, first, lane_count::<R>(), f));
+        Ok(())
+    }
+
+    /// Multiply half of the lanes, starting at lane `first`, into lanes of twice the size.
+    fn vext_mul<T: Lane, R: Lane>(
+        &mut self,
+        first: usize,
+        f: impl Fn(T, T) -> R,
+    ) -> Result<(), Trap> {
+        let b = self.pop_v128()?;
+        let a = self.pop_v128()?;
+        self.push_v128(widen(a, b, first, f));
+        Ok(())
+    }
+
+    /// Convert the first `count` lanes to a different type. The remaining lanes are zero.
+    fn vconvert<T: Lane, R: Lane>(&mut self, count: usize, f: impl Fn(T) -> R) -> Result<(), Trap> {
+        let a = self.pop_v128()?;
+        self.push_v128(convert(a, 0, count, f));
+        Ok(())
+    }
+
+    fn all_true<T: Lane + PartialEq + Default>(&mut self) -> Result<(), Trap> {
+        let a = self.pop_v128()?;
+        self.stack_push(all_true::<T>(a) as i32);
+        Ok(())
+    }
+
+    fn bitmask<T: Lane>(&mut self) -> Result<(), Trap> {
+        let a = self.pop_v128()?;
+        self.stack_push(bitmask::<T>(a));
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use crate::test_utils::call;
+
+#[test]
+fn empty_block_type() {
+    let wat = r#"(module
+        (func (export "main") (result i32) (local i32)
+            (block
+                (local.set 0 (i32.const 3)))
+            (local.get 0)))"#;
+    assert_eq!(call::<(), i32>(wat, "main", ()).unwrap(), 3);
+}
+
+#[test]
+fn value_block_type() {
+    let wat = r#"(module
+        (func (export "main") (result i64)
+            (block (result i64)
+                (i64.const 7))))"#;
+    assert_eq!(call::<(), i64>(wat, "main", ()).unwrap(), 7);
+}
+
+#[test]
+fn type_index_block_type() {
+    let wat = r#"(module
+        (type $swap (func (param i32 i32) (result i32 i32)))
+        (func (export "main") (result i32) (local $x i32) (local $y i32)
+            (i32.const 10)
+            (i32.const 3)
+            ;; Both parameters are taken from the stack of the function
+            (block (type $swap)
+                (local.set $y)
+                (local.set $x)
+                (local.get $y)
+                (local.get $x))
+            (i32.sub)))"#;
+    assert_eq!(call::<(), i32>(wat, "main", ()).unwrap(), -7);
+}
+
+#[test]
+fn loop_with_params() {
+    let wat = r#"(module
+        (func (export "main") (param i32) (result i32) (local $count i32)
+            (local.get 0)
+            ;; The branch back to the loop carries its parameter
+            (loop $again (param i32) (result i32)
+                (local.set $count (i32.add (local.get $count) (i32.const 1)))
+                (i32.sub (i32.const 1))
+                (local.tee 0)
+                (br_if $again (local.get 0)))
+            (drop)
+            (local.get $count)))"#;
+    assert_eq!(call::<i32, i32>(wat, "main", 5).unwrap(), 5);
+}
+
+#[test]
+fn if_with_params_without_else() {
+    let wat = r#"(module
+        (func (export "main") (param i32) (result i32)
+            (i32.const 4)
+            (if (param i32) (result i32) (local.get 0)
+                (then
+                    (i32.mul (i32.const 2))))))"#;
+    assert_eq!(call::<i32, i32>(wat, "main", 1).unwrap(), 8);
+    assert_eq!(call::<i32, i32>(wat, "main", 0).unwrap(), 4);
+}
+
+#[test]
+fn if_else_with_type_index() {
+    let wat = r#"(module
+        (type $binary (func (param i32 i32) (result i32)))
+        (func (export "main") (param i32) (result i32)
+            (i32.const 10)
+            (i32.const 3)
+            (if (type $binary) (local.get 0)
+                (then (i32.add))
+                (else (i32.sub)))))"#;
+    assert_eq!(call::<i32, i32>(wat, "main", 1).unwrap(), 13);
+    assert_eq!(call::<i32, i32>(wat, "main", 0).unwrap(), 7);
+}
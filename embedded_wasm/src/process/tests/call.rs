@@ -0,0 +1,216 @@
+use crate::{
+    test_utils::{call, encode},
+    Dynamic, ExecErrorKind, FfiHandler, Process, ProcessAction, Value, Vec, Wasm,
+};
+
+#[test]
+fn call_local_function() {
+    let wat = r#"(module
+        (func $five (result i32) (i32.const 5))
+        (func (export "main") (result i32)
+            (i32.add (call $five) (i32.const 1))))"#;
+    assert_eq!(call::<(), i32>(wat, "main", ()).unwrap(), 6);
+}
+
+#[test]
+fn arguments_are_the_first_locals() {
+    let wat = r#"(module
+        (func $sub (param $a i32) (param $b i32) (result i32)
+            (local $c i32)
+            (local.set $c (i32.sub (local.get $a) (local.get $b)))
+            (local.get $c))
+        (func (export "main") (result i32)
+            ;; The values below the arguments stay on the stack of the caller
+            (i32.add (i32.const 100) (call $sub (i32.const 10) (i32.const 3)))))"#;
+    assert_eq!(call::<(), i32>(wat, "main", ()).unwrap(), 107);
+}
+
+#[test]
+fn multiple_results() {
+    let wat = r#"(module
+        (func $swap (param i32 i64) (result i64 i32)
+            (local.get 1)
+            (local.get 0))
+        (func (export "main") (result i64 i32)
+            (call $swap (i32.const 1) (i64.const 2))))"#;
+    assert_eq!(call::<(), (i64, i32)>(wat, "main", ()).unwrap(), (2, 1));
+}
+
+#[test]
+fn recursion() {
+    let wat = r#"(module
+        (func $fac (export "fac") (param i64) (result i64)
+            (if (result i64) (i64.eqz (local.get 0))
+                (then (i64.const 1))
+                (else (i64.mul (local.get 0) (call $fac (i64.sub (local.get 0) (i64.const 1))))))))"#;
+    assert_eq!(
+        call::<i64, i64>(wat, "fac", 20).unwrap(),
+        2_432_902_008_176_640_000
+    );
+}
+
+/// Adds the two arguments of any extern function.
+struct Add;
+
+impl FfiHandler for Add {
+    fn handle(&mut self, process: &mut Process, _function: &str, args: Vec<Dynamic>) {
+        process.stack_push(args[0].as_i32() + args[1].as_i32());
+    }
+}
+
+#[test]
+fn spawn_reexported_import() {
+    let bytes = encode(
+        r#"(module
+            (import "env" "g" (func $g (param i32) (result i32)))
+            (export "f" (func $g)))"#,
+    );
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn_with_args("f", &[Value::I32(4)]).unwrap();
+    match process.step().unwrap() {
+        ProcessAction::CallExtern { function, args } => {
+            assert_eq!(function, "g");
+            assert_eq!(args.len(), 1);
+            assert_eq!(args[0].as_i32(), 4);
+        }
+        _ => panic!("Expected a call to an extern function"),
+    }
+    process.stack_push(5);
+    match process.step().unwrap() {
+        ProcessAction::Finished(results) => {
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].as_i32(), 5);
+        }
+        _ => panic!("Expected the process to finish"),
+    }
+    assert!(matches!(
+        process.step().err().unwrap().kind,
+        ExecErrorKind::ProcessFinished
+    ));
+}
+
+#[test]
+fn call_reexported_import() {
+    let bytes = encode(
+        r#"(module
+            (import "env" "add" (func $add (param i32 i32) (result i32)))
+            (export "add" (func $add)))"#,
+    );
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let add = wasm.typed_func::<(i32, i32), i32>("add").unwrap();
+    assert_eq!(add.call(&mut Add, (2, 3)).unwrap(), 5);
+}
+
+#[test]
+fn reexported_import_runs_after_start() {
+    let wat = r#"(module
+        (import "env" "add" (func $add (param i32 i32) (result i32)))
+        (global $g (export "g") (mut i32) (i32.const 0))
+        (func $start (global.set $g (i32.const 7)))
+        (start $start)
+        (export "add" (func $add)))"#;
+    let bytes = encode(wat);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm
+        .spawn_with_args("add", &[Value::I32(1), Value::I32(2)])
+        .unwrap();
+    let mut called = false;
+    let results = loop {
+        match process.step().unwrap() {
+            ProcessAction::None => {}
+            ProcessAction::CallExtern { args, .. } => {
+                // The start function has returned
+                assert_eq!(process.get_global("g").unwrap().as_i32(), 7);
+                assert!(!called);
+                called = true;
+                process.stack_push(args[0].as_i32() + args[1].as_i32());
+            }
+            ProcessAction::Finished(results) => break results,
+        }
+    };
+    assert!(called);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].as_i32(), 3);
+}
+
+#[test]
+fn reexported_import_with_imported_start() {
+    let bytes = encode(
+        r#"(module
+            (import "env" "start" (func $start))
+            (import "env" "g" (func $g (result i32)))
+            (start $start)
+            (export "f" (func $g)))"#,
+    );
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn("f").unwrap();
+    assert!(matches!(
+        process.step().unwrap(),
+        ProcessAction::CallExtern {
+            function: "start",
+            ..
+        }
+    ));
+    assert!(matches!(
+        process.step().unwrap(),
+        ProcessAction::CallExtern { function: "g", .. }
+    ));
+    process.stack_push(9);
+    match process.step().unwrap() {
+        ProcessAction::Finished(results) => assert_eq!(results[0].as_i32(), 9),
+        _ => panic!("Expected the process to finish"),
+    }
+}
+
+const EXTERN_ADD: &str = r#"(module
+    (import "env" "add" (func $add (param i32 i32) (result i32)))
+    (func (export "main") (result i32)
+        (i32.add
+            (i32.const 1000)
+            (i32.sub (i32.const 100) (call $add (i32.const 2) (i32.const 3))))))"#;
+
+#[test]
+fn extern_call_keeps_the_values_of_the_caller() {
+    let bytes = encode(EXTERN_ADD);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn("main").unwrap();
+    let results = loop {
+        match process.step().unwrap() {
+            ProcessAction::None => {}
+            ProcessAction::CallExtern { function, args } => {
+                assert_eq!(function, "add");
+                // Only the declared parameters are passed, the values below them stay on the stack
+                assert_eq!(args.iter().map(Dynamic::as_i32).collect::<Vec<_>>(), [2, 3]);
+                assert_eq!(
+                    process
+                        .stack
+                        .iter()
+                        .map(Dynamic::as_i32)
+                        .collect::<Vec<_>>(),
+                    [1000, 100]
+                );
+                process.stack_push(5);
+            }
+            ProcessAction::Finished(results) => break results,
+        }
+    };
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].as_i32(), 1095);
+}
+
+#[test]
+fn extern_call_with_wrong_result_count() {
+    let bytes = encode(EXTERN_ADD);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    for pushed in [0, 2] {
+        let mut process = wasm.spawn("main").unwrap();
+        while !matches!(process.step().unwrap(), ProcessAction::CallExtern { .. }) {}
+        for _ in 0..pushed {
+            process.stack_push(5);
+        }
+        assert!(matches!(
+            process.step().err().unwrap().kind,
+            ExecErrorKind::InvalidExternResultCount { expected: 1, found } if found == pushed
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    test_utils::{call, encode},
+    ProcessAction, Wasm,
+};
+
+#[test]
+fn br_unwinds_to_the_label() {
+    let wat = r#"(module
+        (func (export "main") (result i32)
+            ;; Below the label, kept by the branch
+            (i32.const 1)
+            (block $outer (result i32)
+                ;; Above the label, dropped by the branch
+                (i32.const 2)
+                (block
+                    (i32.const 3)
+                    (br $outer (i32.const 4))))
+            (i32.add)))"#;
+    assert_eq!(call::<(), i32>(wat, "main", ()).unwrap(), 5);
+}
+
+#[test]
+fn br_to_loop_carries_no_values() {
+    let bytes = encode(
+        r#"(module
+            (func (export "main") (result i32) (local $n i32)
+                (local.set $n (i32.const 100))
+                (block $done
+                    (loop $again
+                        ;; Left on the stack by every iteration
+                        (i32.const 7)
+                        (br_if $done (i32.eqz (local.get $n)))
+                        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                        (br $again)))
+                (local.get $n)))"#,
+    );
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn("main").unwrap();
+    let mut max_height = 0;
+    loop {
+        max_height = max_height.max(process.stack.len());
+        if let ProcessAction::Finished(results) = process.step().unwrap() {
+            assert_eq!(results[0].as_i32(), 0);
+            break;
+        }
+    }
+    assert!(max_height <= 3, "The stack grew to {} values", max_height);
+}
+
+#[test]
+fn br_table() {
+    let wat = r#"(module
+        (func (export "main") (param i32) (result i32)
+            (block $default
+                (block $two
+                    (block $one
+                        (block $zero
+                            (br_table $zero $one $two $default (local.get 0)))
+                        (return (i32.const 10)))
+                    (return (i32.const 11)))
+                (return (i32.const 12)))
+            (i32.const 13)))"#;
+    for (index, result) in [(0, 10), (1, 11), (2, 12), (3, 13), (100, 13), (-1, 13)] {
+        assert_eq!(call::<i32, i32>(wat, "main", index).unwrap(), result);
+    }
+}
+
+#[test]
+fn br_table_carries_values() {
+    let wat = r#"(module
+        (func (export "main") (param i32) (result i32)
+            (block $outer (result i32)
+                (block $inner (result i32)
+                    (br_table $inner $outer (i32.const 5) (local.get 0)))
+                (i32.add (i32.const 100)))))"#;
+    assert_eq!(call::<i32, i32>(wat, "main", 0).unwrap(), 105);
+    assert_eq!(call::<i32, i32>(wat, "main", 1).unwrap(), 5);
+    assert_eq!(call::<i32, i32>(wat, "main", 2).unwrap(), 5);
+}
+
+#[test]
+fn br_if() {
+    let wat = r#"(module
+        (func (export "main") (param i32) (result i32)
+            (block $b (result i32)
+                ;; The value stays on the stack when the branch is not taken
+                (drop (br_if $b (i32.const 1) (local.get 0)))
+                (i32.const 2))))"#;
+    assert_eq!(call::<i32, i32>(wat, "main", 0).unwrap(), 2);
+    assert_eq!(call::<i32, i32>(wat, "main", 5).unwrap(), 1);
+}
+
+#[test]
+fn return_from_nested_blocks() {
+    let wat = r#"(module
+        (func $inner (result i32)
+            (i32.const 1)
+            (block
+                (block
+                    (loop
+                        (i32.const 2)
+                        (return (i32.const 3))))))
+        (func (export "main") (result i32)
+            (i32.add (i32.const 10) (call $inner))))"#;
+    assert_eq!(call::<(), i32>(wat, "main", ()).unwrap(), 13);
+}
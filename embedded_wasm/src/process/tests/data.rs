@@ -0,0 +1,67 @@
+use crate::{
+    test_utils::{call, trap},
+    ExecErrorKind, Trap,
+};
+
+#[test]
+fn active_segment_is_copied() {
+    let wat = r#"(module
+        (memory 1)
+        (data (i32.const 8) "\01\02\03\04")
+        (func (export "main") (result i32 i32 i32)
+            (i32.load (i32.const 8))
+            (i32.load8_u (i32.const 7))
+            (i32.load8_u (i32.const 12))))"#;
+    assert_eq!(
+        call::<(), (i32, i32, i32)>(wat, "main", ()).unwrap(),
+        (0x0403_0201, 0, 0)
+    );
+}
+
+#[test]
+fn active_segment_out_of_bounds() {
+    let wat = r#"(module
+        (memory 1)
+        (data (i32.const 65534) "\01\02\03")
+        (func (export "main")))"#;
+    assert!(matches!(
+        call::<(), ()>(wat, "main", ()),
+        Err(ExecErrorKind::InstantiationFailed(Trap::MemoryOutOfBounds))
+    ));
+}
+
+#[test]
+fn memory_init() {
+    let wat = r#"(module
+        (memory 1)
+        (data $passive "\01\02\03\04")
+        (func (export "main") (result i32)
+            (memory.init $passive (i32.const 100) (i32.const 1) (i32.const 2))
+            (i32.load (i32.const 100))))"#;
+    assert_eq!(call::<(), i32>(wat, "main", ()).unwrap(), 0x0302);
+}
+
+#[test]
+fn memory_init_after_data_drop() {
+    let wat = r#"(module
+        (memory 1)
+        (data $passive "\01\02\03\04")
+        (data $active (i32.const 0) "\05")
+        (func (export "dropped") (param i32)
+            (data.drop $passive)
+            (memory.init $passive (i32.const 0) (i32.const 0) (local.get 0)))
+        (func (export "active") (param i32)
+            (memory.init $active (i32.const 0) (i32.const 0) (local.get 0))))"#;
+    assert_eq!(
+        trap(call::<i32, ()>(wat, "dropped", 1)),
+        Trap::MemoryOutOfBounds
+    );
+    // Active segments are dropped once they are copied
+    assert_eq!(
+        trap(call::<i32, ()>(wat, "active", 1)),
+        Trap::MemoryOutOfBounds
+    );
+    // A dropped segment is empty, so copying nothing still succeeds
+    assert!(call::<i32, ()>(wat, "dropped", 0).is_ok());
+    assert!(call::<i32, ()>(wat, "active", 0).is_ok());
+}
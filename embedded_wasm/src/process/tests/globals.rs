@@ -0,0 +1,81 @@
+use crate::{
+    test_utils::{call, call_wasm, encode},
+    ExecErrorKind, Value, Wasm,
+};
+
+const GLOBALS: &str = r#"(module
+    (import "env" "base" (global $base i32))
+    (global $counter (export "counter") (mut i32) (i32.const 10))
+    (global (export "limit") i64 (i64.const 5))
+    (func (export "increment") (result i32)
+        (global.set $counter (i32.add (global.get $counter) (global.get $base)))
+        (global.get $counter)))"#;
+
+#[test]
+fn get_and_set_in_wasm() {
+    assert_eq!(call::<(), i32>(GLOBALS, "increment", ()).unwrap(), 10);
+    let bytes = encode(GLOBALS);
+    let mut wasm = Wasm::parse(&bytes).unwrap();
+    wasm.set_imported_global("env", "base", 3).unwrap();
+    assert_eq!(call_wasm::<(), i32>(&wasm, "increment", ()).unwrap(), 13);
+}
+
+#[test]
+fn get_and_set_from_the_host() {
+    let bytes = encode(GLOBALS);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn("increment").unwrap();
+    assert_eq!(process.get_global("counter").unwrap().as_i32(), 10);
+    assert_eq!(process.get_global("limit").unwrap().as_i64(), 5);
+    process.set_global("counter", 42).unwrap();
+    assert_eq!(process.get_global("counter").unwrap().as_i32(), 42);
+    assert!(matches!(
+        process.get_global("missing").unwrap_err().kind,
+        ExecErrorKind::GlobalNotFound
+    ));
+}
+
+#[test]
+fn set_immutable_global() {
+    let bytes = encode(GLOBALS);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn("increment").unwrap();
+    assert!(matches!(
+        process.set_global("limit", 6i64).unwrap_err().kind,
+        ExecErrorKind::GlobalImmutable
+    ));
+    assert_eq!(process.get_global("limit").unwrap().as_i64(), 5);
+}
+
+#[test]
+fn set_global_with_the_wrong_type() {
+    let bytes = encode(GLOBALS);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn("increment").unwrap();
+    for value in [Value::I64(1), Value::F32(1.0), Value::ExternRef(None)] {
+        assert!(matches!(
+            process.set_global("counter", value).unwrap_err().kind,
+            ExecErrorKind::GlobalTypeMismatch
+        ));
+    }
+    assert_eq!(process.get_global("counter").unwrap().as_i32(), 10);
+}
+
+#[test]
+fn set_imported_global() {
+    let bytes = encode(GLOBALS);
+    let mut wasm = Wasm::parse(&bytes).unwrap();
+    assert!(matches!(
+        wasm.set_imported_global("env", "base", 1.0f64)
+            .unwrap_err()
+            .kind,
+        ExecErrorKind::GlobalTypeMismatch
+    ));
+    assert!(matches!(
+        wasm.set_imported_global("env", "missing", 1)
+            .unwrap_err()
+            .kind,
+        ExecErrorKind::GlobalNotFound
+    ));
+    assert!(wasm.set_imported_global("env", "base", 1).is_ok());
+}
@@ -0,0 +1,48 @@
+use crate::{
+    instruction::FuncIdx,
+    test_utils::{call_wasm, encode},
+    ProcessAction, Vec, Wasm,
+};
+
+/// Calls five functions in turn, which is more than the cache in the tests keeps. `$d` also calls `$a`.
+const WAT: &str = r#"(module
+    (func $a (result i32) (i32.const 1))
+    (func $b (result i32) (i32.const 10))
+    (func $c (result i32) (i32.const 100))
+    (func $d (result i32) (i32.add (i32.const 999) (call $a)))
+    (func (export "main") (result i32) (local $i i32) (local $sum i32)
+        (loop $again
+            (local.set $sum (i32.add (local.get $sum) (call $a)))
+            (local.set $sum (i32.add (local.get $sum) (call $b)))
+            (local.set $sum (i32.add (local.get $sum) (call $c)))
+            (local.set $sum (i32.add (local.get $sum) (call $d)))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $again (i32.lt_u (local.get $i) (i32.const 5))))
+        (local.get $sum)))"#;
+
+#[test]
+fn code_cache_evicts_least_recently_called() {
+    let bytes = encode(WAT);
+    let mut wasm = Wasm::parse(&bytes).unwrap();
+    wasm.set_code_cache_size(2);
+    let mut process = wasm.spawn("main").unwrap();
+    let results = loop {
+        assert!(process.code_cache.len() <= 2);
+        if let ProcessAction::Finished(results) = process.step().unwrap() {
+            break results;
+        }
+    };
+    // `main` is evicted while it runs, but its frame keeps the body
+    assert_eq!(results[0].as_i32(), 5555);
+    // The last calls are `$d` and the `$a` that it calls
+    let cached: Vec<_> = process.code_cache.iter().map(|(f, _)| *f).collect();
+    assert_eq!(cached, [FuncIdx(3), FuncIdx(0)]);
+}
+
+#[test]
+fn code_cache_can_be_disabled() {
+    let bytes = encode(WAT);
+    let mut wasm = Wasm::parse(&bytes).unwrap();
+    wasm.set_code_cache_size(0);
+    assert_eq!(call_wasm::<(), i32>(&wasm, "main", ()).unwrap(), 5555);
+}
@@ -0,0 +1,182 @@
+use crate::{
+    test_utils::{call, call_wasm, encode, trap},
+    ExecErrorKind, Trap, Value, Vec, Wasm,
+};
+
+const MEMORY: &str = r#"(module
+    (memory 1 3)
+    (func (export "store_load") (param i32 i32) (result i32)
+        (i32.store (local.get 0) (local.get 1))
+        (i32.load (local.get 0)))
+    (func (export "load") (param i32) (result i32) (i32.load (local.get 0)))
+    (func (export "load_offset") (param i32) (result i32) (i32.load offset=4 (local.get 0))))"#;
+
+#[test]
+fn store_and_load() {
+    assert_eq!(
+        call::<(i32, i32), i32>(MEMORY, "store_load", (65532, 0x1234_5678)).unwrap(),
+        0x1234_5678
+    );
+}
+
+#[test]
+fn access_that_straddles_the_end() {
+    assert_eq!(call::<i32, i32>(MEMORY, "load", 65532).unwrap(), 0);
+    for address in [65533, 65535, 65536, -1] {
+        assert_eq!(
+            trap(call::<i32, i32>(MEMORY, "load", address)),
+            Trap::MemoryOutOfBounds
+        );
+    }
+    assert_eq!(
+        trap(call::<(i32, i32), i32>(MEMORY, "store_load", (65533, 1))),
+        Trap::MemoryOutOfBounds
+    );
+}
+
+#[test]
+fn offset_and_address_overflow() {
+    assert_eq!(call::<i32, i32>(MEMORY, "load_offset", 65528).unwrap(), 0);
+    assert_eq!(
+        trap(call::<i32, i32>(MEMORY, "load_offset", 65529)),
+        Trap::MemoryOutOfBounds
+    );
+    // The effective address does not fit in 32 bits, and must not wrap around to 0
+    assert_eq!(
+        trap(call::<i32, i32>(MEMORY, "load_offset", -4)),
+        Trap::MemoryOutOfBounds
+    );
+}
+
+const GROW: &str = r#"(module
+    (memory 1 3)
+    (func (export "main") (result i32 i32 i32 i32)
+        (memory.grow (i32.const 2))
+        (memory.grow (i32.const 1))
+        (memory.grow (i32.const 0))
+        (memory.size)))"#;
+
+#[test]
+fn grow_past_the_declared_maximum() {
+    assert_eq!(
+        call::<(), (i32, i32, i32, i32)>(GROW, "main", ()).unwrap(),
+        (1, -1, 3, 3)
+    );
+}
+
+#[test]
+fn grow_past_the_memory_limit() {
+    let bytes = encode(GROW);
+    let mut wasm = Wasm::parse(&bytes).unwrap();
+    wasm.set_memory_limit(2);
+    assert_eq!(
+        call_wasm::<(), (i32, i32, i32, i32)>(&wasm, "main", ()).unwrap(),
+        (-1, 1, 2, 2)
+    );
+}
+
+/// Functions that copy and fill memory, and return the first 8 bytes of memory afterwards.
+const BULK: &str = r#"(module
+    (memory 1)
+    (data (i32.const 0) "\01\02\03\04\05\06\07\08")
+    (data (i32.const 65528) "\11\12\13\14\15\16\17\18")
+    (func (export "copy") (param i32 i32 i32) (result i64)
+        (memory.copy (local.get 0) (local.get 1) (local.get 2))
+        (i64.load (i32.const 0)))
+    (func (export "fill") (param i32 i32 i32) (result i64)
+        (memory.fill (local.get 0) (local.get 1) (local.get 2))
+        (i64.load (i32.const 0))))"#;
+
+/// Run the exported function `name` of [`BULK`] until it traps, and return the trap and the memory at that point.
+fn bulk_trap(name: &str, args: [i32; 3]) -> (Trap, Vec<u8>) {
+    let bytes = encode(BULK);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn_with_args(name, &args.map(Value::I32)).unwrap();
+    loop {
+        match process.step() {
+            Ok(_) => {}
+            Err(error) => match error.kind {
+                ExecErrorKind::Trap { trap, .. } => return (trap, process.memory.clone()),
+                other => panic!("Expected a trap, got {:?}", other),
+            },
+        }
+    }
+}
+
+#[test]
+fn copy_overlapping() {
+    // Copying to a higher address must not overwrite the source before it is read, and the other way around
+    assert_eq!(
+        call::<(i32, i32, i32), i64>(BULK, "copy", (1, 0, 4)).unwrap(),
+        i64::from_le_bytes([1, 1, 2, 3, 4, 6, 7, 8])
+    );
+    assert_eq!(
+        call::<(i32, i32, i32), i64>(BULK, "copy", (0, 1, 4)).unwrap(),
+        i64::from_le_bytes([2, 3, 4, 5, 5, 6, 7, 8])
+    );
+    assert_eq!(
+        call::<(i32, i32, i32), i64>(BULK, "copy", (0, 65530, 6)).unwrap(),
+        i64::from_le_bytes([0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 7, 8])
+    );
+}
+
+#[test]
+fn fill() {
+    // Only the lowest byte of the value is stored
+    assert_eq!(
+        call::<(i32, i32, i32), i64>(BULK, "fill", (2, 0x1AB, 3)).unwrap(),
+        i64::from_le_bytes([1, 2, 0xAB, 0xAB, 0xAB, 6, 7, 8])
+    );
+}
+
+#[test]
+fn zero_length_at_the_end_of_memory() {
+    let unchanged = i64::from_le_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(
+        call::<(i32, i32, i32), i64>(BULK, "copy", (65536, 65536, 0)).unwrap(),
+        unchanged
+    );
+    assert_eq!(
+        call::<(i32, i32, i32), i64>(BULK, "fill", (65536, 0xFF, 0)).unwrap(),
+        unchanged
+    );
+    // An empty range past the end is still out of bounds
+    assert_eq!(
+        trap(call::<(i32, i32, i32), i64>(BULK, "copy", (65537, 0, 0))),
+        Trap::MemoryOutOfBounds
+    );
+    assert_eq!(
+        trap(call::<(i32, i32, i32), i64>(BULK, "copy", (0, 65537, 0))),
+        Trap::MemoryOutOfBounds
+    );
+    assert_eq!(
+        trap(call::<(i32, i32, i32), i64>(BULK, "fill", (65537, 0xFF, 0))),
+        Trap::MemoryOutOfBounds
+    );
+}
+
+#[test]
+fn out_of_bounds_copy_and_fill_write_nothing() {
+    let (trap, memory) = bulk_trap("copy", [65534, 0, 4]);
+    assert_eq!(trap, Trap::MemoryOutOfBounds);
+    assert_eq!(
+        memory[65528..],
+        [0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18]
+    );
+
+    let (trap, memory) = bulk_trap("copy", [0, 65534, 4]);
+    assert_eq!(trap, Trap::MemoryOutOfBounds);
+    assert_eq!(memory[..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+
+    let (trap, memory) = bulk_trap("fill", [65534, 0xFF, 3]);
+    assert_eq!(trap, Trap::MemoryOutOfBounds);
+    assert_eq!(
+        memory[65528..],
+        [0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18]
+    );
+
+    // The length is unsigned, so a negative length is a very long range
+    let (trap, memory) = bulk_trap("fill", [0, 0xFF, -1]);
+    assert_eq!(trap, Trap::MemoryOutOfBounds);
+    assert_eq!(memory[..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+}
@@ -0,0 +1,13 @@
+mod block;
+mod call;
+mod control;
+mod data;
+mod globals;
+#[cfg(feature = "lazy")]
+mod lazy;
+mod memory;
+mod numeric;
+#[cfg(feature = "simd")]
+mod simd;
+mod table;
+mod trap;
@@ -0,0 +1,213 @@
+use crate::{
+    test_utils::{call, trap},
+    ExecErrorKind, Trap,
+};
+
+const I32_BINARY: &str = r#"(module
+    (func (export "div_s") (param i32 i32) (result i32) (i32.div_s (local.get 0) (local.get 1)))
+    (func (export "div_u") (param i32 i32) (result i32) (i32.div_u (local.get 0) (local.get 1)))
+    (func (export "rem_s") (param i32 i32) (result i32) (i32.rem_s (local.get 0) (local.get 1)))
+    (func (export "rem_u") (param i32 i32) (result i32) (i32.rem_u (local.get 0) (local.get 1)))
+    (func (export "shl") (param i32 i32) (result i32) (i32.shl (local.get 0) (local.get 1)))
+    (func (export "shr_s") (param i32 i32) (result i32) (i32.shr_s (local.get 0) (local.get 1)))
+    (func (export "shr_u") (param i32 i32) (result i32) (i32.shr_u (local.get 0) (local.get 1)))
+    (func (export "rotl") (param i32 i32) (result i32) (i32.rotl (local.get 0) (local.get 1)))
+    (func (export "rotr") (param i32 i32) (result i32) (i32.rotr (local.get 0) (local.get 1))))"#;
+
+const I64_BINARY: &str = r#"(module
+    (func (export "div_s") (param i64 i64) (result i64) (i64.div_s (local.get 0) (local.get 1)))
+    (func (export "div_u") (param i64 i64) (result i64) (i64.div_u (local.get 0) (local.get 1)))
+    (func (export "rem_s") (param i64 i64) (result i64) (i64.rem_s (local.get 0) (local.get 1)))
+    (func (export "rem_u") (param i64 i64) (result i64) (i64.rem_u (local.get 0) (local.get 1)))
+    (func (export "shl") (param i64 i64) (result i64) (i64.shl (local.get 0) (local.get 1)))
+    (func (export "shr_u") (param i64 i64) (result i64) (i64.shr_u (local.get 0) (local.get 1)))
+    (func (export "rotl") (param i64 i64) (result i64) (i64.rotl (local.get 0) (local.get 1))))"#;
+
+fn i32_binary(name: &str, a: i32, b: i32) -> Result<i32, ExecErrorKind> {
+    call(I32_BINARY, name, (a, b))
+}
+
+fn i64_binary(name: &str, a: i64, b: i64) -> Result<i64, ExecErrorKind> {
+    call(I64_BINARY, name, (a, b))
+}
+
+#[test]
+fn division_by_zero() {
+    for name in ["div_s", "div_u", "rem_s", "rem_u"] {
+        assert_eq!(
+            trap(i32_binary(name, 1, 0)),
+            Trap::DivisionByZero,
+            "i32.{}",
+            name
+        );
+        assert_eq!(
+            trap(i64_binary(name, 1, 0)),
+            Trap::DivisionByZero,
+            "i64.{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn signed_division_overflow() {
+    assert_eq!(
+        trap(i32_binary("div_s", i32::MIN, -1)),
+        Trap::IntegerOverflow
+    );
+    assert_eq!(
+        trap(i64_binary("div_s", i64::MIN, -1)),
+        Trap::IntegerOverflow
+    );
+    // The remainder is representable, so it does not trap
+    assert_eq!(i32_binary("rem_s", i32::MIN, -1).unwrap(), 0);
+    assert_eq!(i64_binary("rem_s", i64::MIN, -1).unwrap(), 0);
+}
+
+#[test]
+fn division_rounds_towards_zero() {
+    assert_eq!(i32_binary("div_s", -7, 2).unwrap(), -3);
+    assert_eq!(i32_binary("rem_s", -7, 2).unwrap(), -1);
+    assert_eq!(i32_binary("div_u", -7, 2).unwrap(), 0x7FFF_FFFC);
+    assert_eq!(i32_binary("rem_u", -7, 2).unwrap(), 1);
+}
+
+#[test]
+fn shift_counts_are_masked() {
+    assert_eq!(i32_binary("shl", 1, 33).unwrap(), 2);
+    assert_eq!(i32_binary("shl", 1, -1).unwrap(), i32::MIN);
+    assert_eq!(i32_binary("shr_s", i32::MIN, 32).unwrap(), i32::MIN);
+    assert_eq!(i32_binary("shr_s", i32::MIN, 63).unwrap(), -1);
+    assert_eq!(i32_binary("shr_u", i32::MIN, 63).unwrap(), 1);
+    assert_eq!(i64_binary("shl", 1, 65).unwrap(), 2);
+    assert_eq!(i64_binary("shr_u", i64::MIN, 127).unwrap(), 1);
+}
+
+#[test]
+fn rotate_counts_are_masked() {
+    assert_eq!(i32_binary("rotl", 0x8000_0001u32 as i32, 33).unwrap(), 3);
+    assert_eq!(i32_binary("rotr", 3, 33).unwrap(), 0x8000_0001u32 as i32);
+    assert_eq!(i64_binary("rotl", i64::MIN | 1, 65).unwrap(), 3);
+}
+
+#[test]
+fn bit_counts_of_zero() {
+    let wat = r#"(module
+        (func (export "i32") (param i32) (result i32 i32 i32)
+            (i32.clz (local.get 0))
+            (i32.ctz (local.get 0))
+            (i32.popcnt (local.get 0)))
+        (func (export "i64") (param i64) (result i64 i64 i64)
+            (i64.clz (local.get 0))
+            (i64.ctz (local.get 0))
+            (i64.popcnt (local.get 0))))"#;
+    assert_eq!(
+        call::<i32, (i32, i32, i32)>(wat, "i32", 0).unwrap(),
+        (32, 32, 0)
+    );
+    assert_eq!(
+        call::<i64, (i64, i64, i64)>(wat, "i64", 0).unwrap(),
+        (64, 64, 0)
+    );
+    assert_eq!(
+        call::<i32, (i32, i32, i32)>(wat, "i32", -1).unwrap(),
+        (0, 0, 32)
+    );
+}
+
+#[test]
+fn float_to_integer() {
+    let wat = r#"(module
+        (func (export "trunc") (param f32) (result i32) (i32.trunc_f32_s (local.get 0)))
+        (func (export "trunc_u") (param f64) (result i32) (i32.trunc_f64_u (local.get 0)))
+        (func (export "trunc_sat") (param f32) (result i32) (i32.trunc_sat_f32_s (local.get 0))))"#;
+    assert_eq!(call::<f32, i32>(wat, "trunc", -3.9).unwrap(), -3);
+    assert_eq!(
+        trap(call::<f32, i32>(wat, "trunc", f32::NAN)),
+        Trap::InvalidConversionToInteger
+    );
+    assert_eq!(
+        trap(call::<f32, i32>(wat, "trunc", 2147483648.0)),
+        Trap::IntegerOverflow
+    );
+    assert_eq!(
+        trap(call::<f64, i32>(wat, "trunc_u", -1.0)),
+        Trap::IntegerOverflow
+    );
+    assert_eq!(call::<f64, i32>(wat, "trunc_u", -0.9).unwrap(), 0);
+    assert_eq!(call::<f32, i32>(wat, "trunc_sat", f32::NAN).unwrap(), 0);
+    assert_eq!(call::<f32, i32>(wat, "trunc_sat", 1e10).unwrap(), i32::MAX);
+    assert_eq!(call::<f32, i32>(wat, "trunc_sat", -1e10).unwrap(), i32::MIN);
+}
+
+const FLOAT: &str = r#"(module
+    (func (export "f32.min") (param f32 f32) (result f32) (f32.min (local.get 0) (local.get 1)))
+    (func (export "f32.max") (param f32 f32) (result f32) (f32.max (local.get 0) (local.get 1)))
+    (func (export "f32.nearest") (param f32) (result f32) (f32.nearest (local.get 0)))
+    (func (export "f64.min") (param f64 f64) (result f64) (f64.min (local.get 0) (local.get 1)))
+    (func (export "f64.max") (param f64 f64) (result f64) (f64.max (local.get 0) (local.get 1)))
+    (func (export "f64.nearest") (param f64) (result f64) (f64.nearest (local.get 0))))"#;
+
+#[test]
+fn min_and_max_propagate_nan() {
+    for name in ["f32.min", "f32.max"] {
+        for (a, b) in [(f32::NAN, 1.0), (1.0, f32::NAN), (-f32::NAN, f32::INFINITY)] {
+            assert!(call::<(f32, f32), f32>(FLOAT, name, (a, b))
+                .unwrap()
+                .is_nan());
+        }
+    }
+    for name in ["f64.min", "f64.max"] {
+        for (a, b) in [
+            (f64::NAN, 1.0),
+            (1.0, f64::NAN),
+            (-f64::NAN, f64::NEG_INFINITY),
+        ] {
+            assert!(call::<(f64, f64), f64>(FLOAT, name, (a, b))
+                .unwrap()
+                .is_nan());
+        }
+    }
+}
+
+#[test]
+fn min_and_max_of_signed_zeros() {
+    // -0 is less than +0, in either order
+    for (a, b) in [(0.0, -0.0), (-0.0, 0.0)] {
+        let min = call::<(f32, f32), f32>(FLOAT, "f32.min", (a, b)).unwrap();
+        assert_eq!(min.to_bits(), (-0.0f32).to_bits());
+        let max = call::<(f32, f32), f32>(FLOAT, "f32.max", (a, b)).unwrap();
+        assert_eq!(max.to_bits(), 0.0f32.to_bits());
+        let min = call::<(f64, f64), f64>(FLOAT, "f64.min", (a as f64, b as f64)).unwrap();
+        assert_eq!(min.to_bits(), (-0.0f64).to_bits());
+        let max = call::<(f64, f64), f64>(FLOAT, "f64.max", (a as f64, b as f64)).unwrap();
+        assert_eq!(max.to_bits(), 0.0f64.to_bits());
+    }
+}
+
+#[test]
+fn nearest_rounds_ties_to_even() {
+    for (value, nearest) in [
+        (0.5, 0.0f64),
+        (1.5, 2.0),
+        (2.5, 2.0),
+        (-0.5, -0.0),
+        (-2.5, -2.0),
+        (3.5, 4.0),
+        (2.4, 2.0),
+        (-0.4, -0.0),
+    ] {
+        let result = call::<f32, f32>(FLOAT, "f32.nearest", value as f32).unwrap();
+        assert_eq!(result.to_bits(), (nearest as f32).to_bits(), "{}", value);
+        let result = call::<f64, f64>(FLOAT, "f64.nearest", value).unwrap();
+        assert_eq!(result.to_bits(), nearest.to_bits(), "{}", value);
+    }
+    // The largest `f32` below 0.5 does not round up
+    let below_half = f32::from_bits(0.5f32.to_bits() - 1);
+    assert_eq!(
+        call::<f32, f32>(FLOAT, "f32.nearest", below_half)
+            .unwrap()
+            .to_bits(),
+        0.0f32.to_bits()
+    );
+}
@@ -0,0 +1,251 @@
+//! Tests for the vector instructions, with values that are taken from the spec tests.
+
+#[cfg(not(feature = "lazy"))]
+use crate::test_utils::parse_error;
+#[cfg(feature = "lazy")]
+use crate::{test_utils::call_wasm, ExecErrorKind, Wasm};
+use crate::{
+    test_utils::{call, encode},
+    ErrorKind,
+};
+
+fn v128(wat: &str) -> [u8; 16] {
+    let wat = [r#"(module (func (export "main") (result v128) "#, wat, "))"].concat();
+    call::<(), [u8; 16]>(&wat, "main", ()).unwrap()
+}
+
+fn i32_result(wat: &str) -> i32 {
+    let wat = [r#"(module (func (export "main") (result i32) "#, wat, "))"].concat();
+    call::<(), i32>(&wat, "main", ()).unwrap()
+}
+
+fn i8s(lanes: [i8; 16]) -> [u8; 16] {
+    lanes.map(|lane| lane as u8)
+}
+
+fn i16s(lanes: [i16; 8]) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    for (chunk, lane) in bytes.chunks_exact_mut(2).zip(lanes) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    bytes
+}
+
+fn i32s(lanes: [i32; 4]) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    for (chunk, lane) in bytes.chunks_exact_mut(4).zip(lanes) {
+        chunk.copy_from_slice(&lane.to_le_bytes());
+    }
+    bytes
+}
+
+fn f32s(bytes: [u8; 16]) -> [f32; 4] {
+    let mut lanes = [0.0; 4];
+    for (lane, chunk) in lanes.iter_mut().zip(bytes.chunks_exact(4)) {
+        *lane = f32::from_le_bytes(chunk.try_into().unwrap());
+    }
+    lanes
+}
+
+#[test]
+fn swizzle() {
+    assert_eq!(
+        v128(
+            "(i8x16.swizzle
+                (v128.const i8x16 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31)
+                (v128.const i8x16 0 15 16 255 1 2 3 4 5 6 7 8 9 10 11 128))"
+        ),
+        [16, 31, 0, 0, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 0]
+    );
+}
+
+#[test]
+fn shuffle() {
+    assert_eq!(
+        v128(
+            "(i8x16.shuffle 0 16 1 17 2 18 3 19 31 30 29 28 15 14 13 12
+                (v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)
+                (v128.const i8x16 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31))"
+        ),
+        [0, 16, 1, 17, 2, 18, 3, 19, 31, 30, 29, 28, 15, 14, 13, 12]
+    );
+}
+
+#[test]
+fn shuffle_lane_out_of_range() {
+    let mut bytes = encode(
+        r#"(module (func (export "main") (result v128)
+            (i8x16.shuffle 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 7
+                (v128.const i64x2 0 0)
+                (v128.const i64x2 0 0))))"#,
+    );
+    // Lanes can only select one of the 32 bytes of both operands
+    let lane = bytes.iter().rposition(|byte| *byte == 7).unwrap();
+    bytes[lane] = 32;
+    #[cfg(not(feature = "lazy"))]
+    let error = parse_error(&bytes);
+    #[cfg(feature = "lazy")]
+    let error = {
+        let wasm = Wasm::parse(&bytes).unwrap();
+        match call_wasm::<(), [u8; 16]>(&wasm, "main", ()) {
+            Err(ExecErrorKind::InvalidFunction { error, .. }) => error,
+            other => panic!("Expected an invalid function, got {:?}", other),
+        }
+    };
+    assert!(matches!(error, ErrorKind::InvalidLaneIndex { max: 32 }));
+}
+
+#[test]
+fn narrow_saturates() {
+    let operands = "(v128.const i16x8 0 127 128 -128 -129 300 -300 1)
+        (v128.const i16x8 32767 -32768 5 -5 0 0 0 0)";
+    assert_eq!(
+        v128(&["(i8x16.narrow_i16x8_s ", operands, ")"].concat()),
+        i8s([0, 127, 127, -128, -128, 127, -128, 1, 127, -128, 5, -5, 0, 0, 0, 0])
+    );
+    assert_eq!(
+        v128(&["(i8x16.narrow_i16x8_u ", operands, ")"].concat()),
+        [0, 127, 128, 0, 0, 255, 0, 1, 255, 0, 5, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(
+        v128(
+            "(i16x8.narrow_i32x4_s
+                (v128.const i32x4 32768 -32769 1 -1)
+                (v128.const i32x4 0x7fffffff 0x80000000 0 0))"
+        ),
+        i16s([32767, -32768, 1, -1, 32767, -32768, 0, 0])
+    );
+}
+
+#[test]
+fn q15mulr_sat() {
+    assert_eq!(
+        v128(
+            "(i16x8.q15mulr_sat_s
+                (v128.const i16x8 -32768 16384 3 -1 1 -3 0 32767)
+                (v128.const i16x8 -32768 16384 16384 16384 16384 16384 5 32767))"
+        ),
+        i16s([32767, 8192, 2, 0, 1, -1, 0, 32766])
+    );
+}
+
+#[test]
+fn dot() {
+    assert_eq!(
+        v128(
+            "(i32x4.dot_i16x8_s
+                (v128.const i16x8 1 2 3 4 -32768 -32768 100 -100)
+                (v128.const i16x8 5 6 7 8 -32768 -32768 3 3))"
+        ),
+        i32s([17, 53, i32::MIN, 0])
+    );
+}
+
+#[test]
+fn extadd_pairwise() {
+    let operand = "(v128.const i8x16 127 127 -128 -128 1 -1 0 0 0 0 0 0 0 0 0 0)";
+    assert_eq!(
+        v128(&["(i16x8.extadd_pairwise_i8x16_s ", operand, ")"].concat()),
+        i16s([254, -256, 0, 0, 0, 0, 0, 0])
+    );
+    assert_eq!(
+        v128(&["(i16x8.extadd_pairwise_i8x16_u ", operand, ")"].concat()),
+        i16s([254, 256, 256, 0, 0, 0, 0, 0])
+    );
+    assert_eq!(
+        v128("(i32x4.extadd_pairwise_i16x8_u (v128.const i16x8 -1 -1 1 2 0 0 0 0))"),
+        i32s([131070, 3, 0, 0])
+    );
+}
+
+#[test]
+fn shift_counts_are_masked() {
+    assert_eq!(
+        v128("(i8x16.shl (v128.const i8x16 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1) (i32.const 9))"),
+        [2; 16]
+    );
+    assert_eq!(
+        v128("(i8x16.shr_u (v128.const i8x16 -128 -128 -128 -128 -128 -128 -128 -128 -128 -128 -128 -128 -128 -128 -128 -128) (i32.const 15))"),
+        [1; 16]
+    );
+    assert_eq!(
+        v128("(i16x8.shr_s (v128.const i16x8 -32768 -32768 -32768 -32768 -32768 -32768 -32768 -32768) (i32.const 31))"),
+        [0xFF; 16]
+    );
+    assert_eq!(
+        v128("(i32x4.shl (v128.const i32x4 1 2 3 4) (i32.const 33))"),
+        i32s([2, 4, 6, 8])
+    );
+    assert_eq!(
+        v128("(i64x2.shr_u (v128.const i64x2 -1 4) (i32.const -63))"),
+        v128("(v128.const i64x2 0x7fffffffffffffff 2)")
+    );
+}
+
+#[test]
+fn bitmask() {
+    assert_eq!(
+        i32_result("(i8x16.bitmask (v128.const i8x16 -1 0 -1 0 -1 0 -1 0 -1 0 -1 0 -1 0 -1 0))"),
+        0x5555
+    );
+    assert_eq!(
+        i32_result("(i16x8.bitmask (v128.const i16x8 0 -1 0 0 0 0 0 -32768))"),
+        0b1000_0010
+    );
+    assert_eq!(
+        i32_result("(i32x4.bitmask (v128.const i32x4 -1 1 0x80000000 0x7fffffff))"),
+        0b0101
+    );
+    assert_eq!(i32_result("(i64x2.bitmask (v128.const i64x2 1 -1))"), 0b10);
+}
+
+#[test]
+fn trunc_sat() {
+    assert_eq!(
+        v128("(i32x4.trunc_sat_f32x4_s (v128.const f32x4 nan 1e10 -1e10 -2.7))"),
+        i32s([0, i32::MAX, i32::MIN, -2])
+    );
+    assert_eq!(
+        v128("(i32x4.trunc_sat_f32x4_u (v128.const f32x4 -nan -1 5e9 3.9))"),
+        i32s([0, 0, -1, 3])
+    );
+    assert_eq!(
+        v128("(i32x4.trunc_sat_f64x2_s_zero (v128.const f64x2 nan -1e20))"),
+        i32s([0, i32::MIN, 0, 0])
+    );
+}
+
+#[test]
+fn pmin_and_pmax() {
+    // The first operand is returned unless the second one is less (pmin) or greater (pmax), so NaN is not propagated like `min` and `max` do
+    let operands = "(v128.const f32x4 nan 1 -0 0) (v128.const f32x4 1 nan 0 -0)";
+    let pmin = f32s(v128(&["(f32x4.pmin ", operands, ")"].concat()));
+    assert!(pmin[0].is_nan());
+    assert_eq!(pmin[1], 1.0);
+    assert_eq!(pmin[2].to_bits(), (-0.0f32).to_bits());
+    assert_eq!(pmin[3].to_bits(), 0.0f32.to_bits());
+    let pmax = f32s(v128(&["(f32x4.pmax ", operands, ")"].concat()));
+    assert!(pmax[0].is_nan());
+    assert_eq!(pmax[1], 1.0);
+    assert_eq!(pmax[2].to_bits(), (-0.0f32).to_bits());
+    assert_eq!(pmax[3].to_bits(), 0.0f32.to_bits());
+    let min = f32s(v128(&["(f32x4.min ", operands, ")"].concat()));
+    assert!(min[0].is_nan() && min[1].is_nan());
+}
+
+#[test]
+fn average_rounds_up() {
+    assert_eq!(
+        v128(
+            "(i8x16.avgr_u
+                (v128.const i8x16 0 1 1 255 255 254 0 0 0 0 0 0 0 0 0 0)
+                (v128.const i8x16 0 0 2 255 0 255 0 0 0 0 0 0 0 0 0 0))"
+        ),
+        [0, 1, 2, 255, 128, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+    );
+    // The lanes are unsigned, so -1 is 65535
+    assert_eq!(
+        v128("(i16x8.avgr_u (v128.const i16x8 0 1 -1 -1 3 0 0 0) (v128.const i16x8 0 0 -1 0 4 0 0 0))"),
+        i16s([0, 1, -1, -32768, 4, 0, 0, 0])
+    );
+}
@@ -0,0 +1,197 @@
+use alloc::string::String;
+
+use crate::{
+    test_utils::{call, trap},
+    ExternRef, Trap,
+};
+
+const INDIRECT: &str = r#"(module
+    (type $binary (func (param i32 i32) (result i32)))
+    (type $unary (func (param i32) (result i32)))
+    (table 4 funcref)
+    (elem (i32.const 0) $add $sub $negate)
+    (func $add (type $binary) (i32.add (local.get 0) (local.get 1)))
+    (func $sub (type $binary) (i32.sub (local.get 0) (local.get 1)))
+    (func $negate (type $unary) (i32.sub (i32.const 0) (local.get 0)))
+    (func (export "main") (param i32) (result i32)
+        (call_indirect (type $binary) (i32.const 10) (i32.const 3) (local.get 0))))"#;
+
+#[test]
+fn call_indirect() {
+    assert_eq!(call::<i32, i32>(INDIRECT, "main", 0).unwrap(), 13);
+    assert_eq!(call::<i32, i32>(INDIRECT, "main", 1).unwrap(), 7);
+}
+
+#[test]
+fn call_indirect_signature_mismatch() {
+    assert_eq!(
+        trap(call::<i32, i32>(INDIRECT, "main", 2)),
+        Trap::IndirectCallTypeMismatch
+    );
+}
+
+#[test]
+fn call_indirect_null_entry() {
+    assert_eq!(
+        trap(call::<i32, i32>(INDIRECT, "main", 3)),
+        Trap::UninitializedElement
+    );
+}
+
+#[test]
+fn call_indirect_out_of_range() {
+    for index in [4, -1] {
+        assert_eq!(
+            trap(call::<i32, i32>(INDIRECT, "main", index)),
+            Trap::TableOutOfBounds
+        );
+    }
+}
+
+#[test]
+fn ref_null_and_is_null() {
+    let wat = r#"(module
+        (elem declare func $f)
+        (func $f)
+        (func (export "func") (result i32 i32)
+            (ref.is_null (ref.null func))
+            (ref.is_null (ref.func $f)))
+        (func (export "extern") (param externref) (result i32)
+            (ref.is_null (local.get 0))))"#;
+    assert_eq!(call::<(), (i32, i32)>(wat, "func", ()).unwrap(), (1, 0));
+    assert_eq!(
+        call::<Option<ExternRef>, i32>(wat, "extern", None).unwrap(),
+        1
+    );
+    assert_eq!(
+        call::<Option<ExternRef>, i32>(wat, "extern", Some(ExternRef(0))).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn table_get_and_set() {
+    let wat = r#"(module
+        (table 2 externref)
+        (func (export "main") (param i32 externref) (result externref externref)
+            (table.set (local.get 0) (local.get 1))
+            (table.get (i32.const 0))
+            (table.get (i32.const 1))))"#;
+    assert_eq!(
+        call::<(i32, Option<ExternRef>), (Option<ExternRef>, Option<ExternRef>)>(
+            wat,
+            "main",
+            (1, Some(ExternRef(7)))
+        )
+        .unwrap(),
+        (None, Some(ExternRef(7)))
+    );
+    assert_eq!(
+        trap(call::<
+            (i32, Option<ExternRef>),
+            (Option<ExternRef>, Option<ExternRef>),
+        >(wat, "main", (2, None))),
+        Trap::TableOutOfBounds
+    );
+}
+
+#[test]
+fn table_grow_and_size() {
+    let wat = r#"(module
+        (table 1 3 externref)
+        (func (export "main") (param externref) (result i32 i32 i32 i32 i32)
+            (table.grow (local.get 0) (i32.const 1))
+            (table.size)
+            (table.grow (local.get 0) (i32.const 2))
+            (table.size)
+            (ref.is_null (table.get (i32.const 1)))))"#;
+    assert_eq!(
+        call::<Option<ExternRef>, (i32, i32, i32, i32, i32)>(wat, "main", Some(ExternRef(1)))
+            .unwrap(),
+        (1, 2, -1, 2, 0)
+    );
+}
+
+/// A table with four null entries, and a passive segment with three functions that return their position in the segment.
+const FUNCTIONS: &str = r#"
+    (type $get (func (result i32)))
+    (table $t 4 funcref)
+    (elem $e func $one $two $three)
+    (elem declare func $one)
+    (func $one (result i32) (i32.const 1))
+    (func $two (result i32) (i32.const 2))
+    (func $three (result i32) (i32.const 3))"#;
+
+fn module(functions: &str) -> String {
+    ["(module", FUNCTIONS, functions, ")"].concat()
+}
+
+#[test]
+fn table_fill() {
+    let wat = module(
+        r#"(func (export "main") (param i32 i32 i32) (result i32)
+            (table.fill $t (local.get 0) (ref.func $one) (local.get 1))
+            (call_indirect (type $get) (local.get 2)))"#,
+    );
+    assert_eq!(
+        call::<(i32, i32, i32), i32>(&wat, "main", (1, 3, 3)).unwrap(),
+        1
+    );
+    assert_eq!(
+        trap(call::<(i32, i32, i32), i32>(&wat, "main", (1, 3, 0))),
+        Trap::UninitializedElement
+    );
+    assert_eq!(
+        trap(call::<(i32, i32, i32), i32>(&wat, "main", (2, 3, 2))),
+        Trap::TableOutOfBounds
+    );
+}
+
+#[test]
+fn table_init() {
+    let wat = module(
+        r#"(func (export "main") (param i32 i32 i32 i32) (result i32)
+            (table.init $t $e (local.get 0) (local.get 1) (local.get 2))
+            (call_indirect (type $get) (local.get 3)))"#,
+    );
+    let init = |args| call::<(i32, i32, i32, i32), i32>(&wat, "main", args);
+    assert_eq!(init((0, 0, 3, 0)).unwrap(), 1);
+    assert_eq!(init((1, 1, 2, 2)).unwrap(), 3);
+    assert_eq!(trap(init((1, 1, 2, 0))), Trap::UninitializedElement);
+    // Both the segment and the table are checked
+    assert_eq!(trap(init((0, 1, 3, 0))), Trap::TableOutOfBounds);
+    assert_eq!(trap(init((2, 0, 3, 0))), Trap::TableOutOfBounds);
+}
+
+#[test]
+fn elem_drop() {
+    let wat = module(
+        r#"(func (export "main") (param i32)
+            (elem.drop $e)
+            (table.init $t $e (i32.const 0) (i32.const 0) (local.get 0)))"#,
+    );
+    assert_eq!(
+        trap(call::<i32, ()>(&wat, "main", 1)),
+        Trap::TableOutOfBounds
+    );
+    // A dropped segment is empty, so copying nothing still succeeds
+    assert!(call::<i32, ()>(&wat, "main", 0).is_ok());
+}
+
+#[test]
+fn table_copy() {
+    let wat = module(
+        r#"(func (export "main") (param i32 i32 i32) (result i32 i32 i32)
+            (table.init $t $e (i32.const 0) (i32.const 0) (i32.const 3))
+            (table.copy $t $t (local.get 0) (local.get 1) (local.get 2))
+            (call_indirect (type $get) (i32.const 0))
+            (call_indirect (type $get) (i32.const 1))
+            (call_indirect (type $get) (i32.const 2)))"#,
+    );
+    let copy = |args| call::<(i32, i32, i32), (i32, i32, i32)>(&wat, "main", args);
+    // The ranges overlap in both directions
+    assert_eq!(copy((1, 0, 2)).unwrap(), (1, 1, 2));
+    assert_eq!(copy((0, 1, 2)).unwrap(), (2, 3, 3));
+    assert_eq!(trap(copy((2, 0, 3))), Trap::TableOutOfBounds);
+    assert_eq!(trap(copy((0, 2, 3))), Trap::TableOutOfBounds);
+}
@@ -0,0 +1,82 @@
+use crate::{
+    instruction::FuncIdx,
+    test_utils::{call, encode},
+    ExecErrorKind, Process, Trap, Vec, Wasm,
+};
+
+#[test]
+fn trap_position() {
+    let wat = r#"(module
+        (import "env" "unused" (func))
+        (func $divide (param i32) (result i32) (local i64)
+            (i32.const 1)
+            (local.get 0)
+            (i32.div_u))
+        (func (export "main") (result i32)
+            (call $divide (i32.const 0))))"#;
+    match call::<(), i32>(wat, "main", ()) {
+        Err(ExecErrorKind::Trap {
+            trap,
+            function,
+            position,
+        }) => {
+            assert_eq!(trap, Trap::DivisionByZero);
+            // The imported function takes up the first index
+            assert_eq!(function, 1);
+            // `i32.div_u` follows `i32.const 1` and `local.get 0`, which are two bytes each
+            assert_eq!(position, 4);
+        }
+        other => panic!("Expected a trap, got {:?}", other),
+    }
+}
+
+#[test]
+fn trap_position_in_entry_function() {
+    let wat = r#"(module
+        (func (export "main")
+            (unreachable)))"#;
+    assert!(matches!(
+        call::<(), ()>(wat, "main", ()),
+        Err(ExecErrorKind::Trap {
+            trap: Trap::Unreachable,
+            function: 0,
+            position: 0,
+        })
+    ));
+}
+
+#[test]
+fn call_stack_overflow() {
+    let wat = r#"(module
+        (func $unused)
+        (func $recurse (export "main")
+            (call $recurse)))"#;
+    assert!(matches!(
+        call::<(), ()>(wat, "main", ()),
+        Err(ExecErrorKind::Trap {
+            trap: Trap::CallStackOverflow,
+            function: 1,
+            position: 0,
+        })
+    ));
+}
+
+#[test]
+fn trap_in_imported_entry_function() {
+    let bytes = encode(
+        r#"(module
+            (import "env" "g" (func $g (param i32)))
+            (export "g" (func $g)))"#,
+    );
+    let wasm = Wasm::parse(&bytes).unwrap();
+    // `spawn_with_args` checks the arguments, so the process is created without them
+    let mut process = Process::new(&wasm, FuncIdx(0), Vec::new()).unwrap();
+    assert!(matches!(
+        process.step().err().unwrap().kind,
+        ExecErrorKind::Trap {
+            trap: Trap::StackUnderflow,
+            function: 0,
+            position: 0,
+        }
+    ));
+}
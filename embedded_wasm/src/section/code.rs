@@ -1,22 +1,30 @@
-use crate::{
-    instruction::{Instruction, ValType},
-    ErrorKind, ParseResult, Reader, Vec,
-};
+use crate::{instruction::ValType, ErrorKind, ParseResult, Reader, Vec};
 
 #[derive(Debug)]
-pub struct Code {
+pub struct Code<'a> {
     pub locals: Vec<(u32, ValType)>,
-    pub expr: Vec<Instruction>,
+    /// The encoded instructions of the function, including the final `end`. These are decoded while the function is validated.
+    pub body: &'a [u8],
 }
 
-impl Code {
-    pub fn parse<'a>(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
+impl<'a> Code<'a> {
+    #[cfg(not(feature = "lazy"))]
+    pub fn parse(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
+        Self::decode(Self::parse_range(reader)?)
+    }
+
+    /// Read the encoded body of a function, including the declarations of its locals, without decoding it.
+    pub fn parse_range(reader: &mut Reader<'a>) -> ParseResult<'a, &'a [u8]> {
         let slice = reader.read_slice()?;
-        let (last_byte, slice) = slice.split_last().unwrap();
-        if *last_byte != 0x0B {
+        if slice.last() != Some(&0x0B) {
             let mark = reader.mark_relative(-1);
             return Err(mark.into_error(ErrorKind::InvalidCode));
         }
+        Ok(slice)
+    }
+
+    /// Decode the declarations of the locals of a body that was read by [`Code::parse_range`].
+    pub fn decode(slice: &'a [u8]) -> ParseResult<'a, Self> {
         let mut reader = Reader::new(slice);
 
         // TODO: Flatten this into a Vec<ValType>?
@@ -26,8 +34,9 @@ impl Code {
             Ok((count, val_type))
         })?;
 
-        let expr = Instruction::parse_vec(&mut reader)?;
-
-        Ok(Self { locals, expr })
+        Ok(Self {
+            locals,
+            body: reader.remaining(),
+        })
     }
 }
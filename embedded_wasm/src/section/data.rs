@@ -1,28 +1,30 @@
-use super::MemIdx;
-use crate::{instruction::Instruction, ParseResult, Reader, Vec};
+use crate::{
+    instruction::{Instruction, MemIdx},
+    ErrorKind, ParseResult, Reader, Vec,
+};
 
 #[derive(Debug)]
 pub struct Data<'a> {
+    /// The memory that an active segment is copied into. This is `None` for memory 0.
     pub memidx: Option<MemIdx>,
+    /// The offset at which an active segment is copied into memory. This is `None` for passive segments, which are only copied by `memory.init`.
     pub expression: Option<Vec<Instruction>>,
     pub bytes: &'a [u8],
 }
 
 impl<'a> Data<'a> {
     pub fn parse(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
-        let mode = reader.read_u8()?;
+        let mark = reader.mark();
+        let mode = reader.read_int::<u32>()?;
         let memidx = if mode == 0x02 {
             Some(reader.read_index()?)
         } else {
             None
         };
-        let expression = if mode == 0x00 || mode == 0x02 {
-            let bytes = reader.read_until(0x0b);
-
-            let mut reader = Reader::new(bytes);
-            Some(Instruction::parse_vec(&mut reader)?)
-        } else {
-            None
+        let expression = match mode {
+            0x00 | 0x02 => Some(Instruction::parse_until_0x0B(reader)?),
+            0x01 => None,
+            _ => return Err(mark.into_error(ErrorKind::InvalidDataMode)),
         };
         let bytes = reader.read_slice()?;
         Ok(Self {
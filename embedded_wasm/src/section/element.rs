@@ -0,0 +1,74 @@
+use crate::{
+    instruction::{FuncIdx, Instruction, RefType, TableIdx},
+    ErrorKind, ParseResult, Reader, Vec,
+};
+
+#[derive(Debug)]
+pub struct Element {
+    pub reftype: RefType,
+    pub mode: ElementMode,
+    /// The constant expressions of the references in this segment. Segments that list function indices are stored as `ref.func` expressions.
+    pub init: Vec<Vec<Instruction>>,
+}
+
+#[derive(Debug)]
+pub enum ElementMode {
+    /// The segment is only copied into a table by `table.init`.
+    Passive,
+    /// The segment is copied into `table` at `offset` when a process is spawned.
+    Active {
+        table: TableIdx,
+        offset: Vec<Instruction>,
+    },
+    /// The segment only declares references for `ref.func`, and can not be copied into a table.
+    Declarative,
+}
+
+impl Element {
+    pub fn parse<'a>(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
+        let mark = reader.mark();
+        let flags = reader.read_int::<u32>()?;
+        if flags > 0x07 {
+            return Err(mark.into_error(ErrorKind::InvalidElementMode));
+        }
+        // bit 0: passive or declarative, bit 1: explicit table index or declarative, bit 2: expressions instead of function indices
+        let mode = match flags & 0x03 {
+            0x00 => ElementMode::Active {
+                table: TableIdx(0),
+                offset: Instruction::parse_until_0x0B(reader)?,
+            },
+            0x02 => ElementMode::Active {
+                table: reader.read_index()?,
+                offset: Instruction::parse_until_0x0B(reader)?,
+            },
+            0x01 => ElementMode::Passive,
+            _ => ElementMode::Declarative,
+        };
+        let uses_expressions = flags & 0x04 != 0;
+        let reftype = if flags & 0x03 == 0x00 {
+            // The short forms are always function references
+            RefType::FuncRef
+        } else if uses_expressions {
+            RefType::parse(reader)?
+        } else {
+            let mark = reader.mark();
+            if reader.read_u8()? != 0x00 {
+                return Err(mark.into_error(ErrorKind::InvalidElementKind));
+            }
+            RefType::FuncRef
+        };
+        let init = if uses_expressions {
+            reader.read_vec(Instruction::parse_until_0x0B)?
+        } else {
+            reader.read_vec(|reader| {
+                let index: FuncIdx = reader.read_index()?;
+                Ok(alloc::vec![Instruction::RefFunc { index }])
+            })?
+        };
+        Ok(Self {
+            reftype,
+            mode,
+            init,
+        })
+    }
+}
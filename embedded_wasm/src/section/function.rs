@@ -1,5 +1,4 @@
-use super::TypeIdx;
-use crate::{ParseResult, Reader};
+use crate::{instruction::TypeIdx, ParseResult, Reader};
 
 #[derive(Debug, Clone)]
 pub struct Function(pub TypeIdx);
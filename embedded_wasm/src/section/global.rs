@@ -5,20 +5,29 @@ use crate::{
 
 #[derive(Debug, Clone)]
 pub struct Global {
-    pub r#type: ValType,
-    pub mutable: bool,
+    pub r#type: GlobalType,
     pub init: Vec<Instruction>,
 }
 
 impl Global {
     pub fn parse<'a>(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
-        let r#type = ValType::parse(reader)?;
-        let mutable = reader.read_u8()? == 0x01;
+        let r#type = GlobalType::parse(reader)?;
         let init = Instruction::parse_until_0x0B(reader)?;
-        Ok(Global {
-            r#type,
-            mutable,
-            init,
-        })
+        Ok(Global { r#type, init })
+    }
+}
+
+/// The type of a defined or imported global.
+#[derive(Debug, Clone)]
+pub struct GlobalType {
+    pub valtype: ValType,
+    pub mutable: bool,
+}
+
+impl GlobalType {
+    pub fn parse<'a>(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
+        let valtype = ValType::parse(reader)?;
+        let mutable = reader.read_u8()? == 0x01;
+        Ok(Self { valtype, mutable })
     }
 }
@@ -1,7 +1,5 @@
-use crate::{
-    instruction::{GlobalIdx, MemIdx, TableIdx, TypeIdx},
-    ErrorKind, ParseResult, Reader,
-};
+use super::{GlobalType, Memory, Table};
+use crate::{instruction::TypeIdx, ErrorKind, ParseResult, Reader};
 
 #[derive(Clone, Debug)]
 pub struct Import<'a> {
@@ -22,9 +20,9 @@ impl<'a> Import<'a> {
         let mark = reader.mark();
         let desc = match reader.read_u8()? {
             0x00 => ImportDescription::Type(reader.read_index()?),
-            0x01 => ImportDescription::Table(reader.read_index()?),
-            0x02 => ImportDescription::Memory(reader.read_index()?),
-            0x03 => ImportDescription::Global(reader.read_index()?),
+            0x01 => ImportDescription::Table(Table::parse(reader)?),
+            0x02 => ImportDescription::Memory(Memory::parse(reader)?),
+            0x03 => ImportDescription::Global(GlobalType::parse(reader)?),
             _ => return Err(mark.into_error(ErrorKind::UnknownImportDescription)),
         };
         Ok(Self {
@@ -37,7 +35,7 @@ impl<'a> Import<'a> {
 #[derive(Clone, Debug)]
 pub enum ImportDescription {
     Type(TypeIdx),
-    Table(TableIdx),
-    Memory(MemIdx),
-    Global(GlobalIdx),
+    Table(Table),
+    Memory(Memory),
+    Global(GlobalType),
 }
@@ -1,5 +1,5 @@
-use super::{Limit, RefType};
-use crate::{ParseResult, Reader};
+use super::Limit;
+use crate::{instruction::RefType, ParseResult, Reader};
 
 #[derive(Debug, Clone)]
 pub struct Table {
@@ -0,0 +1,19 @@
+use crate::{instruction::ValType, ErrorKind, ParseResult, Reader, Vec};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Type {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+impl Type {
+    pub fn parse<'a>(reader: &mut Reader<'a>) -> ParseResult<'a, Self> {
+        let mark = reader.mark();
+        if reader.read_u8()? != 0x60 {
+            return Err(mark.into_error(ErrorKind::InvalidTypeHeader));
+        }
+        let params = reader.read_vec(ValType::parse)?;
+        let results = reader.read_vec(ValType::parse)?;
+        Ok(Self { params, results })
+    }
+}
@@ -0,0 +1,56 @@
+//! Helpers for the tests, which run modules that are written in the WebAssembly text format.
+
+use core::fmt::Debug;
+
+use crate::{
+    Dynamic, ErrorKind, ExecErrorKind, FfiHandler, Process, Trap, Vec, Wasm, WasmParams,
+    WasmResults,
+};
+
+/// Encode a module that is written in the text format.
+pub fn encode(wat: &str) -> Vec<u8> {
+    wat::parse_str(wat).unwrap()
+}
+
+/// The reason that `Wasm::parse` rejects the given module.
+pub fn parse_error(bytes: &[u8]) -> ErrorKind {
+    Wasm::parse(bytes).unwrap_err().kind
+}
+
+/// Call the exported function `name` of the given module, and run it to completion.
+pub fn call<P: WasmParams, R: WasmResults>(
+    wat: &str,
+    name: &str,
+    args: P,
+) -> Result<R, ExecErrorKind> {
+    let bytes = encode(wat);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    call_wasm(&wasm, name, args)
+}
+
+/// Call the exported function `name` of a parsed module, and run it to completion.
+pub fn call_wasm<'a, P: WasmParams, R: WasmResults>(
+    wasm: &'a Wasm<'a>,
+    name: &str,
+    args: P,
+) -> Result<R, ExecErrorKind> {
+    let func = wasm.typed_func::<P, R>(name).map_err(|error| error.kind)?;
+    func.call(&mut NoImports, args).map_err(|error| error.kind)
+}
+
+/// The trap of a call that is expected to trap.
+pub fn trap<R: Debug>(result: Result<R, ExecErrorKind>) -> Trap {
+    match result {
+        Err(ExecErrorKind::Trap { trap, .. }) => trap,
+        other => panic!("Expected a trap, got {:?}", other),
+    }
+}
+
+/// A handler for modules that do not call extern functions.
+pub struct NoImports;
+
+impl FfiHandler for NoImports {
+    fn handle(&mut self, _process: &mut Process, function: &str, _args: Vec<Dynamic>) {
+        panic!("Unexpected call to extern function {:?}", function);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    instruction::{FuncIdx, NumType, RefType, ValType},
+    process::Dynamic,
+    ExecResult, ExternRef, FfiHandler, Process, ProcessAction, Vec, Wasm,
+};
+use core::marker::PhantomData;
+
+/// A handle to an exported function with a known signature. This is created by using [`Wasm`]'s `typed_func()` function.
+pub struct TypedFunc<'a, Params, Results> {
+    wasm: &'a Wasm<'a>,
+    func: FuncIdx,
+    _marker: PhantomData<fn(Params) -> Results>,
+}
+
+impl<'a, Params: WasmParams, Results: WasmResults> TypedFunc<'a, Params, Results> {
+    pub(crate) fn new(wasm: &'a Wasm<'a>, func: FuncIdx) -> Self {
+        Self {
+            wasm,
+            func,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Spawn a new process that runs this function with the given `args`, and run it to completion.
+    ///
+    /// Any extern function that the wasm code calls is passed to `handler`.
+    pub fn call(&self, handler: &mut impl FfiHandler, args: Params) -> ExecResult<'a, Results> {
+        let mut process = Process::new(self.wasm, self.func, args.into_args())?;
+        loop {
+            match process.step()? {
+                ProcessAction::None => {}
+                ProcessAction::CallExtern { function, args } => {
+                    handler.handle(&mut process, function, args)
+                }
+                ProcessAction::Finished(results) => return Ok(Results::from_results(&results)),
+            }
+        }
+    }
+}
+
+/// A rust type that can be passed to and returned from a wasm function.
+///
+/// This is implemented for `i32`, `i64`, `f32`, `f64`, `Option<ExternRef>` and `[u8; 16]` (`v128`, with the `simd` feature).
+pub trait WasmType: Sized {
+    #[doc(hidden)]
+    fn val_type() -> ValType;
+    #[doc(hidden)]
+    fn from_dynamic(val: &Dynamic) -> Self;
+    #[doc(hidden)]
+    fn into_dynamic(self) -> Dynamic;
+}
+
+macro_rules! impl_wasm_type {
+    ($($ty:ty => $val_type:expr, $getter:ident;)*) => {
+        $(
+            impl WasmType for $ty {
+                fn val_type() -> ValType {
+                    $val_type
+                }
+                fn from_dynamic(val: &Dynamic) -> Self {
+                    val.$getter()
+                }
+                fn into_dynamic(self) -> Dynamic {
+                    self.into()
+                }
+            }
+        )*
+    };
+}
+
+impl_wasm_type! {
+    i32 => ValType::Num(NumType::I32), as_i32;
+    i64 => ValType::Num(NumType::I64), as_i64;
+    f32 => ValType::Num(NumType::F32), as_f32;
+    f64 => ValType::Num(NumType::F64), as_f64;
+    Option<ExternRef> => ValType::Ref(RefType::ExternRef), as_extern_ref;
+}
+
+#[cfg(feature = "simd")]
+impl_wasm_type! {
+    [u8; 16] => ValType::V128, as_v128;
+}
+
+/// The parameters of a [`TypedFunc`]. This is implemented for `()`, any [`WasmType`] and tuples of up to 8 [`WasmType`]s.
+pub trait WasmParams {
+    #[doc(hidden)]
+    fn matches(params: &[ValType]) -> bool;
+    #[doc(hidden)]
+    fn into_args(self) -> Vec<Dynamic>;
+}
+
+/// The results of a [`TypedFunc`]. This is implemented for `()`, any [`WasmType`] and tuples of up to 8 [`WasmType`]s.
+pub trait WasmResults {
+    #[doc(hidden)]
+    fn matches(results: &[ValType]) -> bool;
+    #[doc(hidden)]
+    fn from_results(results: &[Dynamic]) -> Self;
+}
+
+impl<T: WasmType> WasmParams for T {
+    fn matches(params: &[ValType]) -> bool {
+        params == [T::val_type()]
+    }
+    fn into_args(self) -> Vec<Dynamic> {
+        alloc::vec![self.into_dynamic()]
+    }
+}
+
+impl<T: WasmType> WasmResults for T {
+    fn matches(results: &[ValType]) -> bool {
+        results == [T::val_type()]
+    }
+    fn from_results(results: &[Dynamic]) -> Self {
+        T::from_dynamic(&results[0])
+    }
+}
+
+macro_rules! impl_wasm_tuple {
+    ($($name:ident $idx:tt),*) => {
+        impl<$($name: WasmType),*> WasmParams for ($($name,)*) {
+            fn matches(params: &[ValType]) -> bool {
+                params == [$($name::val_type()),*]
+            }
+            fn into_args(self) -> Vec<Dynamic> {
+                alloc::vec![$(self.$idx.into_dynamic()),*]
+            }
+        }
+
+        impl<$($name: WasmType),*> WasmResults for ($($name,)*) {
+            fn matches(results: &[ValType]) -> bool {
+                results == [$($name::val_type()),*]
+            }
+            #[allow(unused_variables, clippy::unused_unit)]
+            fn from_results(results: &[Dynamic]) -> Self {
+                ($($name::from_dynamic(&results[$idx]),)*)
+            }
+        }
+    };
+}
+
+impl_wasm_tuple!();
+impl_wasm_tuple!(A 0);
+impl_wasm_tuple!(A 0, B 1);
+impl_wasm_tuple!(A 0, B 1, C 2);
+impl_wasm_tuple!(A 0, B 1, C 2, D 3);
+impl_wasm_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_wasm_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_wasm_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_wasm_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
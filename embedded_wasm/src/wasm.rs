@@ -1,38 +1,85 @@
 use crate::{
-    instruction::{FuncIdx, SectionType},
+    bytecode::Body,
+    instruction::{FuncIdx, GlobalIdx, SectionType, TypeIdx},
     reader::Reader,
-    section, ErrorKind, ExecError, ExecErrorKind, ExecResult, ParseError, ParseResult, Process,
-    Vec,
+    section,
+    typed_func::{TypedFunc, WasmParams, WasmResults},
+    Dynamic, ErrorKind, ExecError, ExecErrorKind, ExecResult, ParseError, ParseResult, Process,
+    Value, Vec,
 };
 
+#[cfg(test)]
+mod tests;
+mod validate;
+
+/// The size of a single page of linear memory.
+pub(crate) const PAGE_SIZE: usize = 65536;
+/// The maximum amount of pages that a 32-bit linear memory can have.
+const MAX_PAGES: u32 = 65536;
+/// The maximum amount of locals of a function that `Wasm::parse` accepts, including its parameters. Every local takes up 16 bytes in every call to the function.
+const DEFAULT_LOCALS_LIMIT: usize = 256;
+/// The amount of decoded function bodies that a process keeps by default, see `Wasm::set_code_cache_size`.
+#[cfg(feature = "lazy")]
+const DEFAULT_CODE_CACHE_SIZE: usize = 8;
+
 /// A reference to a parsed WASM file.
 ///
 /// This is created by calling `parse`, and can be executed by calling `spawn`.
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct Wasm<'a> {
-    // types: Vec<section::Type>,
+    types: Vec<section::Type>,
     imports: Vec<section::Import<'a>>,
-    // functions: Vec<section::Function>,
-    /// A reference to the memory segment in this wasm file
-    #[cfg(feature = "parse-memory")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "parse-memory")))]
-    pub memory: section::Memory,
-
-    /// A reference to the globals in this wasm file
-    #[cfg(feature = "parse-globals")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "parse-globals")))]
+    /// The amount of imports that are functions. These take up the first indices of the function index space.
+    imported_function_count: usize,
+    functions: Vec<section::Function>,
+    /// A reference to the memory segment in this wasm file. This is either defined or imported by the wasm file.
+    pub memory: Option<section::Memory>,
+    /// The maximum amount of pages that a process is allowed to allocate, see `set_memory_limit`.
+    memory_limit: Option<u32>,
+    /// The maximum amount of locals of a function, see `parse_with_locals_limit`.
+    locals_limit: usize,
+
+    /// A reference to the globals in this wasm file. These come after the imported globals in the global index space.
     pub globals: Vec<section::Global>,
+    /// The values of the imported globals, see `set_imported_global`.
+    imported_globals: Vec<Dynamic>,
 
     exports: Vec<section::Export<'a>>,
-    code: Vec<section::Code>,
-    // data: Vec<section::Data<'a>>,
-    // table: Vec<section::Table>,
+    /// The lowered bodies of the defined functions.
+    #[cfg(not(feature = "lazy"))]
+    code: Vec<Body<'a>>,
+    /// The encoded bodies of the defined functions. These are decoded when a process calls them, see `decode_code`.
+    #[cfg(feature = "lazy")]
+    code: Vec<&'a [u8]>,
+    /// The context that the function bodies are validated in when they are decoded, see `decode_code`.
+    #[cfg(feature = "lazy")]
+    module: validate::Module,
+    /// The amount of decoded function bodies that a process keeps, see `set_code_cache_size`.
+    #[cfg(feature = "lazy")]
+    code_cache_size: usize,
+    pub(crate) data: Vec<section::Data<'a>>,
+    /// The imported and defined tables, in the order of the table index space.
+    pub(crate) tables: Vec<section::Table>,
+    pub(crate) elements: Vec<section::Element>,
+    /// The function that is called when a process is spawned, before the entry function.
+    pub(crate) start: Option<FuncIdx>,
 }
 
 impl<'a> Wasm<'a> {
     /// Parse a wasm file.
+    ///
+    /// The module is validated before it is returned, an invalid module returns `ErrorKind::Invalid` with the position of the first error.
+    /// With the `lazy` feature the function bodies are not decoded yet, an invalid body is reported by the process that calls it.
     pub fn parse(slice: &'a [u8]) -> ParseResult<'a, Self> {
+        Self::parse_with_locals_limit(slice, DEFAULT_LOCALS_LIMIT)
+    }
+
+    /// Parse a wasm file, like `parse`, but allow functions to have up to `limit` locals, including their parameters. `parse` allows 256 locals.
+    ///
+    /// Every local takes up 16 bytes for every call to the function, so this limits the memory that a single frame can use.
+    /// A function with more locals is invalid, and is reported with `ValidationErrorKind::TooManyLocals`.
+    pub fn parse_with_locals_limit(slice: &'a [u8], limit: usize) -> ParseResult<'a, Self> {
         let mut reader = Reader::new(slice);
         let mark = reader.mark();
         if &reader.read_exact()? != b"\0asm" {
@@ -49,91 +96,146 @@ impl<'a> Wasm<'a> {
             });
         }
 
-        // let mut types = Vec::new();
+        let mut types = Vec::new();
         let mut imports = Vec::new();
-        // let mut functions = Vec::new();
-        #[cfg(feature = "parse-memory")]
+        let mut functions = Vec::new();
         let mut memory = None;
-        #[cfg(feature = "parse-globals")]
         let mut globals = Vec::new();
         let mut exports = Vec::new();
         let mut code = Vec::new();
-        // let mut data = Vec::new();
-        // let mut table = Vec::new();
+        let mut data = Vec::new();
+        let mut data_count = None;
+        let mut memory_count = 0;
+        let mut tables = Vec::new();
+        let mut elements = Vec::new();
+        let mut start = None;
 
+        // A bit for every kind of section that was read, only custom sections can be repeated
+        let mut read_sections = 0u16;
         while !reader.is_empty() {
+            let mark = reader.mark();
             let section_type = SectionType::parse(&mut reader)?;
+            if section_type != SectionType::Custom {
+                let bit = 1 << section_type as u16;
+                if read_sections & bit != 0 {
+                    return Err(mark.into_error(ErrorKind::DuplicateSection));
+                }
+                read_sections |= bit;
+            }
             let section = reader.read_slice()?;
             let mut reader = Reader::new(section);
             match section_type {
                 SectionType::Type => {
-                    // assert!(types.is_empty());
-                    // types = reader.read_vec(section::Type::parse)?;
+                    types = reader.read_vec(section::Type::parse)?;
                 }
                 SectionType::Import => {
-                    assert!(imports.is_empty());
                     imports = reader.read_vec(|r| section::Import::parse(r))?;
                 }
                 SectionType::Function => {
-                    // assert!(functions.is_empty());
-                    // functions = reader.read_vec(section::Function::parse)?;
+                    functions = reader.read_vec(section::Function::parse)?;
                 }
                 SectionType::Memory => {
-                    #[cfg(feature = "parse-memory")]
-                    {
-                        assert!(memory.is_none());
-                        let mut memories = reader.read_vec(section::Memory::parse)?;
-                        assert!(memories.len() == 1);
-                        memory = Some(memories.remove(0));
-                    }
+                    let mut memories = reader.read_vec(section::Memory::parse)?;
+                    memory_count += memories.len();
+                    memory = memories.pop();
                 }
                 SectionType::Global => {
-                    #[cfg(feature = "parse-globals")]
-                    {
-                        assert!(globals.is_empty());
-                        globals = reader.read_vec(section::Global::parse)?;
-                    }
+                    globals = reader.read_vec(section::Global::parse)?;
                 }
                 SectionType::Export => {
-                    assert!(exports.is_empty());
                     exports = reader.read_vec(section::Export::parse)?;
                 }
                 SectionType::Code => {
-                    assert!(code.is_empty());
-                    code = reader.read_vec(section::Code::parse)?;
+                    #[cfg(not(feature = "lazy"))]
+                    {
+                        code = reader.read_vec(section::Code::parse)?;
+                    }
+                    #[cfg(feature = "lazy")]
+                    {
+                        code = reader.read_vec(section::Code::parse_range)?;
+                    }
                 }
                 SectionType::Data => {
-                    // assert!(data.is_empty());
-                    // data = reader.read_vec(section::Data::parse)?;
+                    data = reader.read_vec(section::Data::parse)?;
+                }
+                SectionType::DataCount => {
+                    data_count = Some(reader.read_int::<u32>()?);
                 }
                 SectionType::Table => {
-                    // assert!(table.is_empty());
-                    // table = reader.read_vec(section::Table::parse)?;
+                    tables = reader.read_vec(section::Table::parse)?;
+                }
+                SectionType::Element => {
+                    elements = reader.read_vec(section::Element::parse)?;
+                }
+                SectionType::Start => {
+                    start = Some(reader.read_index()?);
                 }
                 SectionType::Custom => {
                     // ignored
                 }
-                x => panic!(
-                    "WARNING: Section type {:?}\n  {:02x?}\n  {}",
-                    x,
-                    reader.remaining(),
-                    alloc::string::String::from_utf8_lossy(reader.remaining())
-                ),
             }
         }
-        Ok(Self {
-            // types,
+        if data_count.is_some_and(|count| count as usize != data.len()) {
+            return Err(reader.mark().into_error(ErrorKind::DataCountMismatch));
+        }
+        memory_count += imports
+            .iter()
+            .filter(|i| matches!(i.desc, section::ImportDescription::Memory(_)))
+            .count();
+        let memory = memory.or_else(|| {
+            imports.iter().find_map(|i| match &i.desc {
+                section::ImportDescription::Memory(memory) => Some(memory.clone()),
+                _ => None,
+            })
+        });
+        let tables = imports
+            .iter()
+            .filter_map(|i| match &i.desc {
+                section::ImportDescription::Table(table) => Some(table.clone()),
+                _ => None,
+            })
+            .chain(tables)
+            .collect();
+        let imported_function_count = imports
+            .iter()
+            .filter(|i| matches!(i.desc, section::ImportDescription::Type(_)))
+            .count();
+        let imported_global_count = imports
+            .iter()
+            .filter(|i| matches!(i.desc, section::ImportDescription::Global(_)))
+            .count();
+        let mut wasm = Self {
+            types,
             imports,
-            // functions,
-            #[cfg(feature = "parse-memory")]
-            memory: memory.expect("Missing memory block"),
-            #[cfg(feature = "parse-globals")]
+            imported_function_count,
+            functions,
+            memory,
+            memory_limit: None,
+            locals_limit: limit,
             globals,
+            imported_globals: alloc::vec![Dynamic::default(); imported_global_count],
             exports,
-            code,
-            // data,
-            // table,
-        })
+            code: Vec::new(),
+            #[cfg(feature = "lazy")]
+            module: validate::Module::default(),
+            #[cfg(feature = "lazy")]
+            code_cache_size: DEFAULT_CODE_CACHE_SIZE,
+            data,
+            tables,
+            elements,
+            start,
+        };
+        #[cfg(not(feature = "lazy"))]
+        {
+            wasm.code = validate::validate(&wasm, &code, memory_count, data_count, &reader)?;
+        }
+        #[cfg(feature = "lazy")]
+        {
+            wasm.module =
+                validate::validate_module(&wasm, code.len(), memory_count, data_count, &reader)?;
+            wasm.code = code;
+        }
+        Ok(wasm)
     }
 
     /// Spawn a new process that starts at the given `fn_name` entrypoint.
@@ -144,32 +246,228 @@ impl<'a> Wasm<'a> {
     /// pub extern "C" fn fn_name() { .. }
     /// ```
     pub fn spawn(&'a self, fn_name: &str) -> ExecResult<'a, Process<'a>> {
-        let entry_func_idx = match self
-            .exports
+        self.spawn_with_args(fn_name, &[])
+    }
+
+    /// Spawn a new process that starts at the given `fn_name` entrypoint, and pass the given arguments to it.
+    ///
+    /// The arguments are checked against the signature of the function, this will return `ExecErrorKind::InvalidArgumentCount` or `ExecErrorKind::InvalidArgumentType` if they do not match.
+    /// ```rs
+    /// #[no_mangle]
+    /// pub extern "C" fn on_button(pin: i32) { .. }
+    /// ```
+    /// can be spawned with `wasm.spawn_with_args("on_button", &[Value::I32(pin)])`.
+    pub fn spawn_with_args(&'a self, fn_name: &str, args: &[Value]) -> ExecResult<'a, Process<'a>> {
+        let (entry_func_idx, ty) = self.find_exported_function(fn_name)?;
+        let params = &ty.params;
+        if params.len() != args.len() {
+            return Err(ExecError {
+                wasm: self,
+                kind: ExecErrorKind::InvalidArgumentCount {
+                    expected: params.len(),
+                    found: args.len(),
+                },
+            });
+        }
+        if let Some(index) = params
             .iter()
-            .filter_map(|e| match &e.desc {
-                section::ExportDesc::Function(idx) => Some((idx, e.name)),
+            .zip(args)
+            .position(|(param, arg)| *param != arg.val_type())
+        {
+            return Err(ExecError {
+                wasm: self,
+                kind: ExecErrorKind::InvalidArgumentType { index },
+            });
+        }
+
+        let args = args.iter().map(|&arg| arg.into()).collect();
+        Process::new(self, entry_func_idx, args)
+    }
+
+    /// Get a handle to the exported function `fn_name` that can be called with typed arguments and results.
+    ///
+    /// The signature of the function is checked once here, this will return `ExecErrorKind::InvalidFunctionSignature` if it does not match `Params` and `Results`.
+    /// ```rs
+    /// let add = wasm.typed_func::<(i32, f32), i64>("add")?;
+    /// let result: i64 = add.call(&mut handler, (5, 2.0))?;
+    /// ```
+    pub fn typed_func<Params: WasmParams, Results: WasmResults>(
+        &'a self,
+        fn_name: &str,
+    ) -> ExecResult<'a, TypedFunc<'a, Params, Results>> {
+        let (func, ty) = self.find_exported_function(fn_name)?;
+        if !Params::matches(&ty.params) || !Results::matches(&ty.results) {
+            return Err(ExecError {
+                wasm: self,
+                kind: ExecErrorKind::InvalidFunctionSignature,
+            });
+        }
+        Ok(TypedFunc::new(self, func))
+    }
+
+    /// Limit the amount of pages of linear memory that a process may allocate. Each page is 64 KiB.
+    ///
+    /// This applies to both the initial memory of a spawned process, and any `memory.grow` instruction that the process executes.
+    pub fn set_memory_limit(&mut self, pages: u32) {
+        self.memory_limit = Some(pages);
+    }
+
+    /// The maximum amount of pages of linear memory that a process can grow to.
+    pub(crate) fn max_memory_pages(&self) -> u32 {
+        let declared = self
+            .memory
+            .as_ref()
+            .and_then(|m| m.0.max)
+            .unwrap_or(MAX_PAGES);
+        declared.min(self.memory_limit.unwrap_or(MAX_PAGES))
+    }
+
+    /// Set the value of the imported global `namespace.name`. Imported globals that are not set are initialized to zero.
+    ///
+    /// Fails with [`ExecErrorKind::GlobalNotFound`] if this wasm file does not import a global with the given name, and with [`ExecErrorKind::GlobalTypeMismatch`] if `value` does not have the type of the global.
+    pub fn set_imported_global(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        value: impl Into<Value>,
+    ) -> ExecResult<'_, ()> {
+        let global = self
+            .imports
+            .iter()
+            .filter_map(|i| match &i.desc {
+                section::ImportDescription::Global(global_type) => Some((&i.name, global_type)),
                 _ => None,
             })
-            .find(|(_, name)| *name == fn_name)
-        {
-            Some((idx, _)) => idx,
-            None => {
-                return Err(ExecError {
-                    wasm: self,
-                    kind: ExecErrorKind::FunctionNotFound,
-                })
+            .enumerate()
+            .find_map(|(idx, (import, global_type))| {
+                (import.namespace == namespace && import.name == name)
+                    .then_some((idx, global_type.valtype))
+            });
+        let value = value.into();
+        let kind = match global {
+            None => ExecErrorKind::GlobalNotFound,
+            Some((_, valtype)) if value.val_type() != valtype => ExecErrorKind::GlobalTypeMismatch,
+            Some((idx, _)) => {
+                self.imported_globals[idx] = value.into();
+                return Ok(());
             }
         };
+        Err(ExecError { wasm: self, kind })
+    }
+
+    /// The initial values of the imported globals.
+    pub(crate) fn imported_globals(&self) -> &[Dynamic] {
+        &self.imported_globals
+    }
+
+    /// Get the type of the given global. This works for both imported and defined globals.
+    pub(crate) fn global_type(&self, idx: GlobalIdx) -> &section::GlobalType {
+        let imported = self
+            .imports
+            .iter()
+            .filter_map(|i| match &i.desc {
+                section::ImportDescription::Global(ty) => Some(ty),
+                _ => None,
+            })
+            .nth(idx.0 as usize);
+        match imported {
+            Some(ty) => ty,
+            None => &self.globals[idx.0 as usize - self.imported_globals.len()].r#type,
+        }
+    }
+
+    /// Find the function that is exported with the given name.
+    /// Find the exported function `name`, and its type.
+    fn find_exported_function(
+        &'a self,
+        name: &str,
+    ) -> ExecResult<'a, (FuncIdx, &'a section::Type)> {
+        self.exports
+            .iter()
+            .find_map(|e| match &e.desc {
+                section::ExportDesc::Function(idx) if e.name == name => {
+                    Some((*idx, self.get_function_type(*idx)?))
+                }
+                _ => None,
+            })
+            .ok_or(ExecError {
+                wasm: self,
+                kind: ExecErrorKind::FunctionNotFound,
+            })
+    }
 
-        Ok(Process::new(self, *entry_func_idx))
+    pub(crate) fn find_exported_global(&self, name: &str) -> Option<GlobalIdx> {
+        self.exports.iter().find_map(|e| match &e.desc {
+            section::ExportDesc::Global(idx) if e.name == name => Some(*idx),
+            _ => None,
+        })
     }
 
-    pub(crate) fn get_code(&self, idx: FuncIdx) -> &section::Code {
-        &self.code[idx.0 - self.imports.len()]
+    #[cfg(not(feature = "lazy"))]
+    pub(crate) fn get_code(&self, idx: FuncIdx) -> Option<&Body<'a>> {
+        let index = (idx.0 as usize).checked_sub(self.imported_function_count)?;
+        self.code.get(index)
     }
 
-    pub(crate) fn get_import(&self, idx: FuncIdx) -> Option<&section::Import> {
-        self.imports.get(idx.0)
+    /// Decode and validate the body of the given local function. The rest of the module is validated by `parse`.
+    #[cfg(feature = "lazy")]
+    pub(crate) fn decode_code(&self, idx: FuncIdx) -> Option<ParseResult<'a, Body<'a>>> {
+        let index = (idx.0 as usize).checked_sub(self.imported_function_count)?;
+        let slice = *self.code.get(index)?;
+        Some(
+            section::Code::decode(slice)
+                .and_then(|code| validate::validate_function(self, &self.module, idx, &code)),
+        )
+    }
+
+    /// Set the amount of decoded function bodies that a process keeps. The default is 8.
+    ///
+    /// A function that is not kept is decoded when it is called, and replaces the function that was called least recently.
+    #[cfg(feature = "lazy")]
+    pub fn set_code_cache_size(&mut self, functions: usize) {
+        self.code_cache_size = functions;
+    }
+
+    #[cfg(feature = "lazy")]
+    pub(crate) fn code_cache_size(&self) -> usize {
+        self.code_cache_size
+    }
+
+    /// Get the parameter and result types of the exported function `fn_name`. Returns `None` if there is no such function.
+    ///
+    /// This works for exports of both imported and local functions.
+    pub fn function_type(&self, fn_name: &str) -> Option<&section::Type> {
+        self.exports.iter().find_map(|e| match &e.desc {
+            section::ExportDesc::Function(idx) if e.name == fn_name => self.get_function_type(*idx),
+            _ => None,
+        })
+    }
+
+    /// Get the type of the given function. This works for both imported and local functions.
+    pub(crate) fn get_function_type(&self, idx: FuncIdx) -> Option<&section::Type> {
+        let type_idx = match idx.0 as usize {
+            index if index < self.imported_function_count => self
+                .imports
+                .iter()
+                .filter_map(|import| match import.desc {
+                    section::ImportDescription::Type(type_idx) => Some(type_idx),
+                    _ => None,
+                })
+                .nth(index)?,
+            index => self.functions.get(index - self.imported_function_count)?.0,
+        };
+        self.get_type(type_idx)
+    }
+
+    pub(crate) fn get_type(&self, idx: TypeIdx) -> Option<&section::Type> {
+        self.types.get(idx.0 as usize)
+    }
+
+    /// Get the import of the given function. Returns `None` if the function is defined in this module.
+    pub(crate) fn get_import(&self, idx: FuncIdx) -> Option<&section::Import<'a>> {
+        self.imports
+            .iter()
+            .filter(|i| matches!(i.desc, section::ImportDescription::Type(_)))
+            .nth(idx.0 as usize)
     }
 }
@@ -0,0 +1,209 @@
+use crate::{
+    instruction::{NumType, ValType},
+    test_utils::{call_wasm, encode, parse_error},
+    ErrorKind, ExecErrorKind, ProcessAction, Value, Wasm,
+};
+
+#[test]
+fn duplicate_section() {
+    #[rustfmt::skip]
+    let bytes = [
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+        // Two empty type sections
+        0x01, 0x01, 0x00,
+        0x01, 0x01, 0x00,
+    ];
+    assert!(matches!(parse_error(&bytes), ErrorKind::DuplicateSection));
+}
+
+#[test]
+fn repeated_custom_section() {
+    #[rustfmt::skip]
+    let bytes = [
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+        // Two custom sections named "a"
+        0x00, 0x02, 0x01, b'a',
+        0x00, 0x02, 0x01, b'a',
+    ];
+    assert!(Wasm::parse(&bytes).is_ok());
+}
+
+#[test]
+fn data_count_mismatch() {
+    #[rustfmt::skip]
+    let bytes = [
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+        // A data count section that announces one segment
+        0x0C, 0x01, 0x01,
+        // A data section without segments
+        0x0B, 0x01, 0x00,
+    ];
+    assert!(matches!(parse_error(&bytes), ErrorKind::DataCountMismatch));
+}
+
+#[test]
+fn imported_function_type() {
+    // The imported global does not take up an index in the function index space
+    let bytes = encode(
+        r#"(module
+            (import "env" "g" (global i32))
+            (import "env" "one" (func $one (param i32)))
+            (import "env" "two" (func $two (param i32 i32)))
+            (func (export "main")
+                (call $two (i32.const 1) (i32.const 2))))"#,
+    );
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm.spawn("main").unwrap();
+    loop {
+        match process.step().unwrap() {
+            ProcessAction::None => {}
+            ProcessAction::CallExtern { function, args } => {
+                assert_eq!(function, "two");
+                assert_eq!(args.len(), 2);
+                break;
+            }
+            ProcessAction::Finished(_) => panic!("Expected a call to an extern function"),
+        }
+    }
+}
+
+const ARGUMENTS: &str = r#"(module
+    (func (export "main") (param i32 f64) (result f64)
+        (f64.add (f64.convert_i32_s (local.get 0)) (local.get 1))))"#;
+
+#[test]
+fn spawn_with_args() {
+    let bytes = encode(ARGUMENTS);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let mut process = wasm
+        .spawn_with_args("main", &[Value::I32(2), Value::F64(0.5)])
+        .unwrap();
+    let results = loop {
+        if let ProcessAction::Finished(results) = process.step().unwrap() {
+            break results;
+        }
+    };
+    assert_eq!(results[0].as_f64(), 2.5);
+}
+
+#[test]
+fn spawn_with_wrong_argument_count() {
+    let bytes = encode(ARGUMENTS);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    assert!(matches!(
+        wasm.spawn("main").err().unwrap().kind,
+        ExecErrorKind::InvalidArgumentCount {
+            expected: 2,
+            found: 0
+        }
+    ));
+    let args = [Value::I32(2), Value::F64(0.5), Value::I32(3)];
+    assert!(matches!(
+        wasm.spawn_with_args("main", &args).err().unwrap().kind,
+        ExecErrorKind::InvalidArgumentCount {
+            expected: 2,
+            found: 3
+        }
+    ));
+}
+
+#[test]
+fn spawn_with_wrong_argument_type() {
+    let bytes = encode(ARGUMENTS);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    // The first argument that does not match is reported
+    let args = [Value::I64(2), Value::F32(0.5)];
+    assert!(matches!(
+        wasm.spawn_with_args("main", &args).err().unwrap().kind,
+        ExecErrorKind::InvalidArgumentType { index: 0 }
+    ));
+    let args = [Value::I32(2), Value::I32(0)];
+    assert!(matches!(
+        wasm.spawn_with_args("main", &args).err().unwrap().kind,
+        ExecErrorKind::InvalidArgumentType { index: 1 }
+    ));
+}
+
+#[test]
+fn typed_func_with_wrong_signature() {
+    let bytes = encode(ARGUMENTS);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    assert_eq!(
+        call_wasm::<(i32, f64), f64>(&wasm, "main", (2, 0.5)).unwrap(),
+        2.5
+    );
+    let invalid = |kind| matches!(kind, ExecErrorKind::InvalidFunctionSignature);
+    // The parameters
+    assert!(invalid(
+        wasm.typed_func::<i32, f64>("main").err().unwrap().kind
+    ));
+    assert!(invalid(
+        wasm.typed_func::<(i64, f64), f64>("main")
+            .err()
+            .unwrap()
+            .kind
+    ));
+    assert!(invalid(
+        wasm.typed_func::<(i32, f64, i32), f64>("main")
+            .err()
+            .unwrap()
+            .kind
+    ));
+    // The results
+    assert!(invalid(
+        wasm.typed_func::<(i32, f64), ()>("main")
+            .err()
+            .unwrap()
+            .kind
+    ));
+    assert!(invalid(
+        wasm.typed_func::<(i32, f64), f32>("main")
+            .err()
+            .unwrap()
+            .kind
+    ));
+    assert!(invalid(
+        wasm.typed_func::<(i32, f64), (f64, f64)>("main")
+            .err()
+            .unwrap()
+            .kind
+    ));
+}
+
+#[test]
+fn typed_func_of_unknown_export() {
+    let bytes = encode(r#"(module (global (export "g") i32 (i32.const 0)) (func (export "f")))"#);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    assert!(wasm.typed_func::<(), ()>("f").is_ok());
+    // Only exported functions can be called
+    for name in ["g", "h"] {
+        assert!(matches!(
+            wasm.typed_func::<(), ()>(name).err().unwrap().kind,
+            ExecErrorKind::FunctionNotFound
+        ));
+    }
+}
+
+#[test]
+fn function_type_of_export() {
+    let bytes = encode(
+        r#"(module
+            (import "env" "g" (func $g (param i64) (result f32 f32)))
+            (global (export "global") i32 (i32.const 0))
+            (export "g" (func $g))
+            (func (export "f") (param i32 f64)))"#,
+    );
+    let wasm = Wasm::parse(&bytes).unwrap();
+    let f = wasm.function_type("f").unwrap();
+    assert_eq!(
+        f.params,
+        [ValType::Num(NumType::I32), ValType::Num(NumType::F64)]
+    );
+    assert!(f.results.is_empty());
+    // An export of an imported function
+    let g = wasm.function_type("g").unwrap();
+    assert_eq!(g.params, [ValType::Num(NumType::I64)]);
+    assert_eq!(g.results, [ValType::Num(NumType::F32); 2]);
+    assert!(wasm.function_type("global").is_none());
+    assert!(wasm.function_type("h").is_none());
+}
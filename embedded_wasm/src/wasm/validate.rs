@@ -0,0 +1,1188 @@
+//! Validation of a parsed module, following the validation algorithm in the appendix of the WebAssembly specification.
+//!
+//! Function bodies are decoded one instruction at a time while they are validated. The validator knows the height of the stack at every instruction, so it also resolves the [`Jump`]s that a process uses to execute the control instructions.
+
+use super::{Wasm, MAX_PAGES};
+use crate::{
+    bytecode::{Body, Branch, Jump},
+    instruction::{
+        BlockType, FuncIdx, GlobalIdx, Instruction, MemArg, NumType, RefType, TypeIdx, ValType,
+        VectorInstruction,
+    },
+    section, ErrorKind, Mark, ParseError, ParseResult, Reader, ValidationError,
+    ValidationErrorKind, Vec,
+};
+use alloc::borrow::Cow;
+
+#[cfg(test)]
+mod tests;
+
+const I32: ValType = ValType::Num(NumType::I32);
+const I64: ValType = ValType::Num(NumType::I64);
+const F32: ValType = ValType::Num(NumType::F32);
+const F64: ValType = ValType::Num(NumType::F64);
+const V128: ValType = ValType::V128;
+
+type Result<T = ()> = core::result::Result<T, ValidationErrorKind>;
+
+/// Validate the module, and resolve the jumps of the given function bodies.
+///
+/// `memory_count` is the amount of imported and defined memories, and `data_count` is the value of the data count section, which is required by `memory.init` and `data.drop`.
+/// Errors that are not in a function body are reported at the mark of `end`.
+#[cfg(not(feature = "lazy"))]
+pub(super) fn validate<'a>(
+    wasm: &Wasm,
+    code: &[section::Code<'a>],
+    memory_count: usize,
+    data_count: Option<u32>,
+    end: &Reader<'a>,
+) -> ParseResult<'a, Vec<Body<'a>>> {
+    let module = validate_module(wasm, code.len(), memory_count, data_count, end)?;
+    code.iter()
+        .enumerate()
+        .map(|(index, code)| {
+            let function = FuncIdx((wasm.imported_function_count + index) as u32);
+            validate_function(wasm, &module, function, code)
+        })
+        .collect()
+}
+
+/// Validate everything in the module except for the function bodies, see [`validate`]. `function_count` is the amount of bodies in the code section.
+///
+/// Returns the context that the function bodies are validated in.
+pub(super) fn validate_module<'a>(
+    wasm: &Wasm,
+    function_count: usize,
+    memory_count: usize,
+    data_count: Option<u32>,
+    end: &Reader<'a>,
+) -> ParseResult<'a, Module> {
+    let module_error = |kind| {
+        end.mark()
+            .into_error(ErrorKind::Invalid(module_error(kind)))
+    };
+    let module =
+        Module::new(wasm, function_count, memory_count, data_count).map_err(module_error)?;
+    module.validate_sections(wasm).map_err(module_error)?;
+    Ok(module)
+}
+
+/// Validate the body of the given local function in the context that is returned by [`validate_module`], and resolve its jumps.
+pub(super) fn validate_function<'a>(
+    wasm: &Wasm,
+    module: &Module,
+    function: FuncIdx,
+    code: &section::Code<'a>,
+) -> ParseResult<'a, Body<'a>> {
+    let mut validator = FunctionValidator::new(wasm, module, function);
+    validator.validate(code)?;
+    Ok(Body {
+        locals: code.locals.clone(),
+        code: if cfg!(feature = "flash") {
+            Cow::Borrowed(code.body)
+        } else {
+            Cow::Owned(code.body.to_vec())
+        },
+        jumps: validator.jumps,
+    })
+}
+
+fn module_error(kind: ValidationErrorKind) -> ValidationError {
+    ValidationError {
+        function: None,
+        position: 0,
+        kind,
+    }
+}
+
+/// The context that instructions are validated in, which is computed once for the whole module.
+#[derive(Debug, Default)]
+pub(super) struct Module {
+    /// The types of all imported and defined functions. These are checked to exist in the type section.
+    functions: Vec<TypeIdx>,
+    /// The types of all imported and defined globals.
+    globals: Vec<section::GlobalType>,
+    has_memory: bool,
+    data_count: Option<u32>,
+    /// For every function, whether it is referenced outside of the function bodies. Only these functions can be used by `ref.func`.
+    declared_references: Vec<bool>,
+}
+
+impl Module {
+    /// See [`validate`] for the meaning of `memory_count` and `data_count`.
+    fn new(
+        wasm: &Wasm,
+        function_count: usize,
+        memory_count: usize,
+        data_count: Option<u32>,
+    ) -> Result<Self> {
+        if wasm.functions.len() != function_count {
+            return Err(ValidationErrorKind::FunctionCountMismatch);
+        }
+        let mut functions = Vec::with_capacity(wasm.imported_function_count + wasm.functions.len());
+        let mut globals = Vec::new();
+        for import in &wasm.imports {
+            match &import.desc {
+                section::ImportDescription::Type(idx) => {
+                    get_type(wasm, *idx)?;
+                    functions.push(*idx);
+                }
+                section::ImportDescription::Global(ty) => globals.push(ty.clone()),
+                section::ImportDescription::Table(_) | section::ImportDescription::Memory(_) => {}
+            }
+        }
+        for function in &wasm.functions {
+            get_type(wasm, function.0)?;
+            functions.push(function.0);
+        }
+        globals.extend(wasm.globals.iter().map(|global| global.r#type.clone()));
+        if memory_count > 1 {
+            return Err(ValidationErrorKind::MultipleMemories);
+        }
+
+        let mut declared_references = alloc::vec![false; functions.len()];
+        let expressions = wasm
+            .globals
+            .iter()
+            .map(|global| &global.init)
+            .chain(wasm.elements.iter().flat_map(|element| &element.init));
+        for expression in expressions {
+            if let [Instruction::RefFunc { index }] = expression.as_slice() {
+                if let Some(declared) = declared_references.get_mut(index.0 as usize) {
+                    *declared = true;
+                }
+            }
+        }
+        for export in &wasm.exports {
+            if let section::ExportDesc::Function(index) = export.desc {
+                if let Some(declared) = declared_references.get_mut(index.0 as usize) {
+                    *declared = true;
+                }
+            }
+        }
+
+        Ok(Self {
+            functions,
+            globals,
+            has_memory: memory_count == 1,
+            data_count,
+            declared_references,
+        })
+    }
+
+    /// Validate everything in the module except for the function bodies.
+    fn validate_sections(&self, wasm: &Wasm) -> Result {
+        for table in &wasm.tables {
+            validate_limits(&table.limits, u32::MAX)?;
+        }
+        if let Some(memory) = &wasm.memory {
+            validate_limits(&memory.0, MAX_PAGES)?;
+        }
+        let imported_globals = self.globals.len() - wasm.globals.len();
+        for global in &wasm.globals {
+            // The initializer of a global can only refer to imported globals
+            self.const_expr(&global.init, global.r#type.valtype, imported_globals)?;
+        }
+        for element in &wasm.elements {
+            for init in &element.init {
+                self.const_expr(init, ValType::Ref(element.reftype), self.globals.len())?;
+            }
+            if let section::ElementMode::Active { table, offset } = &element.mode {
+                let table = wasm
+                    .tables
+                    .get(table.0 as usize)
+                    .ok_or(ValidationErrorKind::UnknownTable)?;
+                if table.reftype != element.reftype {
+                    return Err(ValidationErrorKind::TypeMismatch);
+                }
+                self.const_expr(offset, I32, self.globals.len())?;
+            }
+        }
+        for data in &wasm.data {
+            if let Some(offset) = &data.expression {
+                if !self.has_memory || data.memidx.is_some_and(|idx| idx.0 != 0) {
+                    return Err(ValidationErrorKind::UnknownMemory);
+                }
+                self.const_expr(offset, I32, self.globals.len())?;
+            }
+        }
+        if let Some(start) = wasm.start {
+            let ty = self.function_type(wasm, start)?;
+            if !ty.params.is_empty() || !ty.results.is_empty() {
+                return Err(ValidationErrorKind::InvalidStartFunction);
+            }
+        }
+        for (index, export) in wasm.exports.iter().enumerate() {
+            let exists = match export.desc {
+                section::ExportDesc::Function(idx) => (idx.0 as usize) < self.functions.len(),
+                section::ExportDesc::Table(idx) => (idx.0 as usize) < wasm.tables.len(),
+                section::ExportDesc::Memory(idx) => self.has_memory && idx.0 == 0,
+                section::ExportDesc::Global(idx) => (idx.0 as usize) < self.globals.len(),
+            };
+            if !exists {
+                return Err(ValidationErrorKind::UnknownExport);
+            }
+            if wasm.exports[..index].iter().any(|e| e.name == export.name) {
+                return Err(ValidationErrorKind::DuplicateExportName);
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate a constant expression that results in a value of type `expected`. Only the first `globals` globals can be referenced.
+    fn const_expr(&self, expression: &[Instruction], expected: ValType, globals: usize) -> Result {
+        let found = match expression {
+            [Instruction::I32Const(_)] => I32,
+            [Instruction::I64Const(_)] => I64,
+            [Instruction::F32Const(_)] => F32,
+            [Instruction::F64Const(_)] => F64,
+            [Instruction::Vector(VectorInstruction::V128Const(_))] => V128,
+            [Instruction::RefNull { reftype }] => ValType::Ref(*reftype),
+            [Instruction::RefFunc { index }] => {
+                if (index.0 as usize) >= self.functions.len() {
+                    return Err(ValidationErrorKind::UnknownFunction);
+                }
+                ValType::Ref(RefType::FuncRef)
+            }
+            [Instruction::GlobalGet(idx)] => {
+                let global = self
+                    .globals
+                    .get(idx.0 as usize)
+                    .filter(|_| (idx.0 as usize) < globals)
+                    .ok_or(ValidationErrorKind::UnknownGlobal)?;
+                if global.mutable {
+                    return Err(ValidationErrorKind::InvalidConstantExpression);
+                }
+                global.valtype
+            }
+            _ => return Err(ValidationErrorKind::InvalidConstantExpression),
+        };
+        if found != expected {
+            return Err(ValidationErrorKind::TypeMismatch);
+        }
+        Ok(())
+    }
+
+    fn function_type<'w>(&self, wasm: &'w Wasm, idx: FuncIdx) -> Result<&'w section::Type> {
+        let ty = self
+            .functions
+            .get(idx.0 as usize)
+            .ok_or(ValidationErrorKind::UnknownFunction)?;
+        get_type(wasm, *ty)
+    }
+
+    fn global(&self, idx: GlobalIdx) -> Result<&section::GlobalType> {
+        self.globals
+            .get(idx.0 as usize)
+            .ok_or(ValidationErrorKind::UnknownGlobal)
+    }
+
+    fn data(&self, idx: crate::instruction::DataIdx) -> Result {
+        match self.data_count {
+            Some(count) if (idx.0 as usize) < count as usize => Ok(()),
+            _ => Err(ValidationErrorKind::UnknownData),
+        }
+    }
+
+    /// Check that the memory exists, and that the alignment of `memarg` is not larger than `max_align`, the natural alignment of the access.
+    fn memarg(&self, memarg: &MemArg, max_align: u32) -> Result {
+        if !self.has_memory {
+            return Err(ValidationErrorKind::UnknownMemory);
+        }
+        if memarg.align > max_align {
+            return Err(ValidationErrorKind::InvalidAlignment);
+        }
+        Ok(())
+    }
+
+    fn memory(&self) -> Result {
+        if self.has_memory {
+            Ok(())
+        } else {
+            Err(ValidationErrorKind::UnknownMemory)
+        }
+    }
+}
+
+fn get_type<'w>(wasm: &'w Wasm, idx: TypeIdx) -> Result<&'w section::Type> {
+    wasm.types
+        .get(idx.0 as usize)
+        .ok_or(ValidationErrorKind::UnknownType)
+}
+
+fn validate_limits(limits: &section::Limit, max: u32) -> Result {
+    if limits.min > max || limits.max.is_some_and(|m| m > max || m < limits.min) {
+        return Err(ValidationErrorKind::InvalidLimits);
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Else,
+}
+
+/// A block that is being validated.
+struct ControlFrame {
+    kind: FrameKind,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    /// The height of the operand stack when this block was entered, excluding its parameters.
+    height: usize,
+    /// Set after an instruction that never falls through, like `br` or `unreachable`. The stack is polymorphic from this point.
+    unreachable: bool,
+    /// The offset of the first instruction in the block, where a branch to a `loop` continues.
+    start: usize,
+    /// The indices of the jumps to the end of this block, which are resolved when the `end` is reached. For an `if`, the first jump is the one of the `if` itself.
+    fixups: Vec<usize>,
+}
+
+impl ControlFrame {
+    /// The types of the values that a branch to this block takes.
+    fn label_types(&self) -> &[ValType] {
+        if self.kind == FrameKind::Loop {
+            &self.params
+        } else {
+            &self.results
+        }
+    }
+
+    /// The label of a branch to this block. A branch to the end of the block is resolved later, through the jump at index `jump`.
+    fn branch(&mut self, jump: usize) -> Branch {
+        let target = if self.kind == FrameKind::Loop {
+            self.start
+        } else {
+            self.fixups.push(jump);
+            0
+        };
+        Branch {
+            target: target as u32,
+            height: self.height as u32,
+            arity: self.label_types().len() as u32,
+        }
+    }
+}
+
+/// Validates the body of a single function.
+struct FunctionValidator<'m> {
+    wasm: &'m Wasm<'m>,
+    module: &'m Module,
+    function: FuncIdx,
+    locals: Vec<ValType>,
+    /// The types on the operand stack. `None` is a value of unknown type, which is popped from the polymorphic stack of unreachable code.
+    operands: Vec<Option<ValType>>,
+    controls: Vec<ControlFrame>,
+    /// The offset of the instruction that is being validated.
+    offset: usize,
+    jumps: Vec<Jump>,
+}
+
+impl<'m> FunctionValidator<'m> {
+    fn new(wasm: &'m Wasm<'m>, module: &'m Module, function: FuncIdx) -> Self {
+        Self {
+            wasm,
+            module,
+            function,
+            locals: Vec::new(),
+            operands: Vec::new(),
+            controls: Vec::new(),
+            offset: 0,
+            jumps: Vec::new(),
+        }
+    }
+
+    fn validate<'a>(&mut self, code: &section::Code<'a>) -> ParseResult<'a> {
+        let mut reader = Reader::new(code.body);
+        let ty = self
+            .module
+            .function_type(self.wasm, self.function)
+            .map_err(|kind| self.error(reader.mark(), kind))?;
+        let mut count = ty.params.len();
+        for (n, _) in &code.locals {
+            count = count.saturating_add(*n as usize);
+        }
+        if count > self.wasm.locals_limit {
+            return Err(self.error(reader.mark(), ValidationErrorKind::TooManyLocals));
+        }
+        self.locals.reserve(count);
+        self.locals.extend_from_slice(&ty.params);
+        for (n, valtype) in &code.locals {
+            self.locals
+                .extend(core::iter::repeat_n(*valtype, *n as usize));
+        }
+
+        self.push_control(FrameKind::Function, Vec::new(), ty.results.clone(), 0);
+        // The function frame is popped by the final `end`
+        while !self.controls.is_empty() {
+            self.offset = code.body.len() - reader.remaining().len();
+            let mark = reader.mark();
+            let instruction = Instruction::parse(&mut reader)?;
+            if instruction == Instruction::Else
+                && self.controls.last().unwrap().kind != FrameKind::If
+            {
+                return Err(mark.into_error(ErrorKind::DuplicateElse));
+            }
+            let next = code.body.len() - reader.remaining().len();
+            if let Err(kind) = self.instruction(instruction, next) {
+                return Err(self.error(mark, kind));
+            }
+        }
+        if !reader.is_empty() {
+            return Err(reader.mark().into_error(ErrorKind::InvalidCode));
+        }
+        self.jumps.shrink_to_fit();
+        Ok(())
+    }
+
+    /// The error for the invalid instruction at `mark`.
+    fn error<'a>(&self, mark: Mark<'a>, kind: ValidationErrorKind) -> ParseError<'a> {
+        mark.into_error(ErrorKind::Invalid(ValidationError {
+            function: Some(self.function.0 as usize),
+            position: self.offset,
+            kind,
+        }))
+    }
+
+    fn push_value(&mut self, value: Option<ValType>) {
+        self.operands.push(value);
+    }
+
+    fn push_values(&mut self, values: &[ValType]) {
+        self.operands.extend(values.iter().copied().map(Some));
+    }
+
+    /// Pop a value from the operand stack, and check that it has the `expected` type.
+    fn pop_value(&mut self, expected: Option<ValType>) -> Result<Option<ValType>> {
+        let frame = self.controls.last().unwrap();
+        let actual = if self.operands.len() == frame.height {
+            if !frame.unreachable {
+                return Err(ValidationErrorKind::TypeMismatch);
+            }
+            None
+        } else {
+            self.operands.pop().unwrap()
+        };
+        match (actual, expected) {
+            (Some(actual), Some(expected)) if actual != expected => {
+                Err(ValidationErrorKind::TypeMismatch)
+            }
+            (None, expected) => Ok(expected),
+            (actual, _) => Ok(actual),
+        }
+    }
+
+    fn pop(&mut self, expected: ValType) -> Result {
+        self.pop_value(Some(expected)).map(drop)
+    }
+
+    /// Pop values of the given types, the last type is popped first. Returns the popped values.
+    fn pop_values(&mut self, expected: &[ValType]) -> Result<Vec<Option<ValType>>> {
+        let mut values = alloc::vec![None; expected.len()];
+        for (value, expected) in values.iter_mut().zip(expected).rev() {
+            *value = self.pop_value(Some(*expected))?;
+        }
+        Ok(values)
+    }
+
+    /// Pop a reference of any type.
+    fn pop_reference(&mut self) -> Result {
+        match self.pop_value(None)? {
+            Some(ValType::Ref(_)) | None => Ok(()),
+            Some(_) => Err(ValidationErrorKind::TypeMismatch),
+        }
+    }
+
+    /// Enter a block whose first instruction is at `start`.
+    fn push_control(
+        &mut self,
+        kind: FrameKind,
+        params: Vec<ValType>,
+        results: Vec<ValType>,
+        start: usize,
+    ) {
+        let height = self.operands.len();
+        self.push_values(&params);
+        self.controls.push(ControlFrame {
+            kind,
+            params,
+            results,
+            height,
+            unreachable: false,
+            start,
+            fixups: Vec::new(),
+        });
+    }
+
+    /// Leave the current block, and check that exactly its results are on the stack.
+    fn pop_control(&mut self) -> Result<ControlFrame> {
+        let results = self.controls.last().unwrap().results.clone();
+        self.pop_values(&results)?;
+        let frame = self.controls.pop().unwrap();
+        if self.operands.len() != frame.height {
+            return Err(ValidationErrorKind::TypeMismatch);
+        }
+        Ok(frame)
+    }
+
+    /// Mark the rest of the current block as unreachable.
+    fn unreachable(&mut self) {
+        let frame = self.controls.last_mut().unwrap();
+        self.operands.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn label(&mut self, depth: usize) -> Result<&mut ControlFrame> {
+        let idx = self
+            .controls
+            .len()
+            .checked_sub(depth + 1)
+            .ok_or(ValidationErrorKind::UnknownLabel)?;
+        Ok(&mut self.controls[idx])
+    }
+
+    /// Add a jump for the current instruction to `branch`. Returns the index of the jump.
+    fn push_jump(&mut self, branch: Branch) -> usize {
+        self.jumps.push(Jump {
+            offset: self.offset as u32,
+            branch,
+        });
+        self.jumps.len() - 1
+    }
+
+    /// Add a jump for the current instruction to the label at `depth`. Returns the types of the values that the branch takes.
+    fn branch(&mut self, depth: usize) -> Result<Vec<ValType>> {
+        let jump = self.jumps.len();
+        let label = self.label(depth)?;
+        let (branch, types) = (label.branch(jump), label.label_types().to_vec());
+        self.push_jump(branch);
+        Ok(types)
+    }
+
+    /// Set the target of a jump that was added before its target was known.
+    fn resolve(&mut self, jump: usize, target: usize) {
+        self.jumps[jump].branch.target = target as u32;
+    }
+
+    fn local(&self, idx: usize) -> Result<ValType> {
+        self.locals
+            .get(idx)
+            .copied()
+            .ok_or(ValidationErrorKind::UnknownLocal)
+    }
+
+    fn table(&self, idx: crate::instruction::TableIdx) -> Result<RefType> {
+        self.wasm
+            .tables
+            .get(idx.0 as usize)
+            .map(|table| table.reftype)
+            .ok_or(ValidationErrorKind::UnknownTable)
+    }
+
+    fn element(&self, idx: crate::instruction::ElemIdx) -> Result<RefType> {
+        self.wasm
+            .elements
+            .get(idx.0 as usize)
+            .map(|element| element.reftype)
+            .ok_or(ValidationErrorKind::UnknownElement)
+    }
+
+    fn block_type(&self, bt: &BlockType) -> Result<(Vec<ValType>, Vec<ValType>)> {
+        Ok(match bt {
+            BlockType::Empty => (Vec::new(), Vec::new()),
+            BlockType::ValType(valtype) => (Vec::new(), alloc::vec![*valtype]),
+            BlockType::Type(idx) => {
+                let ty = get_type(self.wasm, *idx)?;
+                (ty.params.clone(), ty.results.clone())
+            }
+        })
+    }
+
+    /// Enter a `block`, `loop` or `if` with the given type, whose first instruction is at `start`.
+    fn block(&mut self, kind: FrameKind, bt: &BlockType, start: usize) -> Result {
+        let (params, results) = self.block_type(bt)?;
+        self.pop_values(&params)?;
+        self.push_control(kind, params, results, start);
+        Ok(())
+    }
+
+    /// Pop and push the operands and results of an instruction with a fixed signature.
+    fn operation(&mut self, params: &[ValType], results: &[ValType]) -> Result {
+        self.pop_values(params)?;
+        self.push_values(results);
+        Ok(())
+    }
+
+    /// Validate a single instruction, and resolve the jumps of control instructions. `next` is the offset of the next instruction.
+    fn instruction(&mut self, instruction: Instruction, next: usize) -> Result {
+        use Instruction::*;
+        match instruction {
+            Block { bt } => self.block(FrameKind::Block, &bt, next)?,
+            Loop { bt } => self.block(FrameKind::Loop, &bt, next)?,
+            If { bt } => {
+                self.pop(I32)?;
+                self.block(FrameKind::If, &bt, next)?;
+                let jump = self.push_jump(Default::default());
+                self.controls.last_mut().unwrap().fixups.push(jump);
+            }
+            Else => {
+                let mut frame = self.pop_control()?;
+                // A false condition continues at the first instruction of the `else` branch
+                let jump = frame.fixups.remove(0);
+                self.resolve(jump, next);
+                self.push_control(FrameKind::Else, frame.params, frame.results, next);
+                frame.fixups.push(self.push_jump(Default::default()));
+                self.controls.last_mut().unwrap().fixups = frame.fixups;
+            }
+            End => {
+                let frame = self.pop_control()?;
+                let target = if frame.kind == FrameKind::Function {
+                    // Branching to the function continues at its `end`, which returns
+                    self.offset
+                } else {
+                    // An `if` without `else` passes its parameters through when the condition is false
+                    if frame.kind == FrameKind::If && frame.params != frame.results {
+                        return Err(ValidationErrorKind::TypeMismatch);
+                    }
+                    self.push_values(&frame.results);
+                    next
+                };
+                for jump in frame.fixups {
+                    self.resolve(jump, target);
+                }
+            }
+            Branch { index } => {
+                let types = self.branch(index.0 as usize)?;
+                self.pop_values(&types)?;
+                self.unreachable();
+            }
+            BranchIf { index } => {
+                self.pop(I32)?;
+                let types = self.branch(index.0 as usize)?;
+                self.pop_values(&types)?;
+                self.push_values(&types);
+            }
+            BranchTable { table } => {
+                self.pop(I32)?;
+                let arity = self.label(table.default.0 as usize)?.label_types().len();
+                // The default is the last jump
+                for label in table.labels.iter().chain([&table.default]) {
+                    let types = self.branch(label.0 as usize)?;
+                    if types.len() != arity {
+                        return Err(ValidationErrorKind::TypeMismatch);
+                    }
+                    let values = self.pop_values(&types)?;
+                    self.operands.extend(values);
+                }
+                self.unreachable();
+            }
+            Return => {
+                let types = self.controls[0].results.clone();
+                self.pop_values(&types)?;
+                self.unreachable();
+            }
+            instruction => self.check_instruction(&instruction)?,
+        }
+        Ok(())
+    }
+
+    /// Validate an instruction that does not change the control flow.
+    fn check_instruction(&mut self, instruction: &Instruction) -> Result {
+        use Instruction::*;
+        let module = self.module;
+        match instruction {
+            Unreachable => self.unreachable(),
+            Nop => {}
+            Call { function } => {
+                let ty = module.function_type(self.wasm, *function)?;
+                self.operation(&ty.params, &ty.results)?;
+            }
+            CallIndirect {
+                type_idx,
+                table_idx,
+            } => {
+                if self.table(*table_idx)? != RefType::FuncRef {
+                    return Err(ValidationErrorKind::TypeMismatch);
+                }
+                let ty = get_type(self.wasm, *type_idx)?;
+                self.pop(I32)?;
+                self.operation(&ty.params, &ty.results)?;
+            }
+
+            RefNull { reftype } => self.push_values(&[ValType::Ref(*reftype)]),
+            RefIsNull => {
+                self.pop_reference()?;
+                self.push_values(&[I32]);
+            }
+            RefFunc { index } => {
+                match module.declared_references.get(index.0 as usize) {
+                    None => return Err(ValidationErrorKind::UnknownFunction),
+                    Some(false) => return Err(ValidationErrorKind::UndeclaredFunctionReference),
+                    Some(true) => {}
+                }
+                self.push_values(&[ValType::Ref(RefType::FuncRef)]);
+            }
+
+            Drop => {
+                self.pop_value(None)?;
+            }
+            Select => {
+                self.pop(I32)?;
+                let first = self.pop_value(None)?;
+                let second = self.pop_value(None)?;
+                let is_reference = |value: Option<ValType>| matches!(value, Some(ValType::Ref(_)));
+                if is_reference(first) || is_reference(second) {
+                    return Err(ValidationErrorKind::TypeMismatch);
+                }
+                if let (Some(first), Some(second)) = (first, second) {
+                    if first != second {
+                        return Err(ValidationErrorKind::TypeMismatch);
+                    }
+                }
+                self.push_value(first.or(second));
+            }
+            SelectVal { val } => {
+                self.operation(&[*val, *val, I32], &[*val])?;
+            }
+
+            LocalGet(idx) => {
+                let valtype = self.local(idx.0 as usize)?;
+                self.push_values(&[valtype]);
+            }
+            LocalSet(idx) => {
+                let valtype = self.local(idx.0 as usize)?;
+                self.pop(valtype)?;
+            }
+            LocalTee(idx) => {
+                let valtype = self.local(idx.0 as usize)?;
+                self.operation(&[valtype], &[valtype])?;
+            }
+            GlobalGet(idx) => {
+                let global = module.global(*idx)?;
+                self.push_values(&[global.valtype]);
+            }
+            GlobalSet(idx) => {
+                let global = module.global(*idx)?;
+                if !global.mutable {
+                    return Err(ValidationErrorKind::ImmutableGlobal);
+                }
+                self.pop(global.valtype)?;
+            }
+
+            TableGet { index } => {
+                let reftype = ValType::Ref(self.table(*index)?);
+                self.operation(&[I32], &[reftype])?;
+            }
+            TableSet { index } => {
+                let reftype = ValType::Ref(self.table(*index)?);
+                self.operation(&[I32, reftype], &[])?;
+            }
+            TableInit { y, x } => {
+                if self.element(*y)? != self.table(*x)? {
+                    return Err(ValidationErrorKind::TypeMismatch);
+                }
+                self.operation(&[I32, I32, I32], &[])?;
+            }
+            TableDrop { x } => {
+                self.element(*x)?;
+            }
+            TableCopy { x, y } => {
+                if self.table(*x)? != self.table(*y)? {
+                    return Err(ValidationErrorKind::TypeMismatch);
+                }
+                self.operation(&[I32, I32, I32], &[])?;
+            }
+            TableGrow { x } => {
+                let reftype = ValType::Ref(self.table(*x)?);
+                self.operation(&[reftype, I32], &[I32])?;
+            }
+            TableSize { x } => {
+                self.table(*x)?;
+                self.push_values(&[I32]);
+            }
+            TableFill { x } => {
+                let reftype = ValType::Ref(self.table(*x)?);
+                self.operation(&[I32, reftype, I32], &[])?;
+            }
+
+            Load { numtype, memarg } => {
+                module.memarg(memarg, natural_alignment(*numtype))?;
+                self.operation(&[I32], &[ValType::Num(*numtype)])?;
+            }
+            Load8 {
+                numtype, memarg, ..
+            } => {
+                module.memarg(memarg, 0)?;
+                self.operation(&[I32], &[ValType::Num(*numtype)])?;
+            }
+            Load16 {
+                numtype, memarg, ..
+            } => {
+                module.memarg(memarg, 1)?;
+                self.operation(&[I32], &[ValType::Num(*numtype)])?;
+            }
+            Load32 { memarg, .. } => {
+                module.memarg(memarg, 2)?;
+                self.operation(&[I32], &[I64])?;
+            }
+            Store { numtype, memarg } => {
+                module.memarg(memarg, natural_alignment(*numtype))?;
+                self.operation(&[I32, ValType::Num(*numtype)], &[])?;
+            }
+            Store8 { numtype, memarg } => {
+                module.memarg(memarg, 0)?;
+                self.operation(&[I32, ValType::Num(*numtype)], &[])?;
+            }
+            Store16 { numtype, memarg } => {
+                module.memarg(memarg, 1)?;
+                self.operation(&[I32, ValType::Num(*numtype)], &[])?;
+            }
+            Store32 { numtype, memarg } => {
+                module.memarg(memarg, 2)?;
+                self.operation(&[I32, ValType::Num(*numtype)], &[])?;
+            }
+            MemorySize => {
+                module.memory()?;
+                self.push_values(&[I32]);
+            }
+            MemoryGrow => {
+                module.memory()?;
+                self.operation(&[I32], &[I32])?;
+            }
+            MemoryInit { index } => {
+                module.memory()?;
+                module.data(*index)?;
+                self.operation(&[I32, I32, I32], &[])?;
+            }
+            DataDrop { index } => module.data(*index)?,
+            MemoryCopy | MemoryFill => {
+                module.memory()?;
+                self.operation(&[I32, I32, I32], &[])?;
+            }
+
+            Vector(instruction) => {
+                if let Some((memarg, max_align)) = vector_memarg(instruction) {
+                    module.memarg(&memarg, max_align)?;
+                }
+                let (params, results) = vector_signature(instruction);
+                self.operation(params, results)?;
+            }
+            instruction => {
+                let (params, results) = numeric_signature(instruction);
+                self.operation(params, results)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The largest alignment exponent that is allowed for a full width memory access of the given type.
+fn natural_alignment(numtype: NumType) -> u32 {
+    match numtype {
+        NumType::I32 | NumType::F32 => 2,
+        NumType::I64 | NumType::F64 => 3,
+    }
+}
+
+type Signature = (&'static [ValType], &'static [ValType]);
+
+/// The operands and results of a numeric instruction.
+fn numeric_signature(instruction: &Instruction) -> Signature {
+    use Instruction::*;
+    match instruction {
+        I32Const(_) => (&[], &[I32]),
+        I64Const(_) => (&[], &[I64]),
+        F32Const(_) => (&[], &[F32]),
+        F64Const(_) => (&[], &[F64]),
+
+        I32EqualZero
+        | I32CountLeadingZeroBits
+        | I32CountTrailingZeroBits
+        | I32CountNonZeroBits
+        | I32Extend8Signed
+        | I32Extend16Signed => (&[I32], &[I32]),
+        I32Equals
+        | I32NotEquals
+        | I32LessThanSigned
+        | I32LessThanUnsigned
+        | I32GreaterThanSigned
+        | I32GreaterThanUnsigned
+        | I32LessOrEqualToSigned
+        | I32LessOrEqualToUnsigned
+        | I32GreaterOrEqualToSigned
+        | I32GreaterOrEqualToUnsigned
+        | I32Add
+        | I32Sub
+        | I32Mul
+        | I32DivSigned
+        | I32DivUnsigned
+        | I32RemainderSigned
+        | I32RemainderUnsigned
+        | I32LogicalAnd
+        | I32LogicalOr
+        | I32LogicalXor
+        | I32ShiftLeft
+        | I32ShiftRightSigned
+        | I32ShiftRightUnsigned
+        | I32RotateLeft
+        | I32RotateRight => (&[I32, I32], &[I32]),
+
+        I64EqualZero | I32WrapI64 => (&[I64], &[I32]),
+        I64Equals
+        | I64NotEquals
+        | I64LessThanSigned
+        | I64LessThanUnsigned
+        | I64GreaterThanSigned
+        | I64GreaterThanUnsigned
+        | I64LessOrEqualToSigned
+        | I64LessOrEqualToUnsigned
+        | I64GreaterOrEqualToSigned
+        | I64GreaterOrEqualToUnsigned => (&[I64, I64], &[I32]),
+        I64CountLeadingZeroBits
+        | I64CountTrailingZeroBits
+        | I64CountNonZeroBits
+        | I64Extend8Signed
+        | I64Extend16Signed
+        | I64Extend32Signed => (&[I64], &[I64]),
+        I64Add
+        | I64Sub
+        | I64Mul
+        | I64DivSigned
+        | I64DivUnsigned
+        | I64RemainderSigned
+        | I64RemainderUnsigned
+        | I64LogicalAnd
+        | I64LogicalOr
+        | I64LogicalXor
+        | I64ShiftLeft
+        | I64ShiftRightSigned
+        | I64ShiftRightUnsigned
+        | I64RotateLeft
+        | I64RotateRight => (&[I64, I64], &[I64]),
+
+        F32Equals | F32NotEquals | F32LessThan | F32GreaterThan | F32LessOrEqualTo
+        | F32GreaterOrEqualTo => (&[F32, F32], &[I32]),
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => (&[F32], &[F32]),
+        F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32CopySign => (&[F32, F32], &[F32]),
+
+        F64Equals | F64NotEquals | F64LessThan | F64GreaterThan | F64LessOrEqualTo
+        | F64GreaterOrEqualTo => (&[F64, F64], &[I32]),
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => (&[F64], &[F64]),
+        F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64CopySign => (&[F64, F64], &[F64]),
+
+        I32TruncF32Signed
+        | I32TruncF32Unsigned
+        | I32TruncSatF32Signed
+        | I32TruncSatF32Unsigned
+        | I32ReinterpretAsF32 => (&[F32], &[I32]),
+        I32TruncF64Signed | I32TruncF64Unsigned | I32TruncSatF64Signed | I32TruncSatF64Unsigned => {
+            (&[F64], &[I32])
+        }
+        I64ExtendI32Signed | I64ExtendI32Unsigned => (&[I32], &[I64]),
+        I64TruncF32Signed | I64TruncF32Unsigned | I64TruncSatF32Signed | I64TruncSatF32Unsigned => {
+            (&[F32], &[I64])
+        }
+        I64TruncF64Signed
+        | I64TruncF64Unsigned
+        | I64TruncSatF64Signed
+        | I64TruncSatF64Unsigned
+        | I64ReinterpretAsF64 => (&[F64], &[I64]),
+        F32ConvertI32Signed | F32ConvertI32Unsigned | F32ReinterpretAsI32 => (&[I32], &[F32]),
+        F32ConvertI64Signed | F32ConvertI64Unsigned => (&[I64], &[F32]),
+        F32DemoteF64 => (&[F64], &[F32]),
+        F64ConvertI32Signed | F64ConvertI32Unsigned => (&[I32], &[F64]),
+        F64ConvertI64Signed | F64ConvertI64Unsigned | F64ReinterpretAsI64 => (&[I64], &[F64]),
+        F64PromoteF32 => (&[F32], &[F64]),
+
+        x => unreachable!("{:?} is not a numeric instruction", x),
+    }
+}
+
+/// The memory argument of a vector instruction that accesses memory, and the largest alignment exponent it allows.
+fn vector_memarg(instruction: &VectorInstruction) -> Option<(MemArg, u32)> {
+    use VectorInstruction::*;
+    Some(match *instruction {
+        V128Load(memarg) | V128Store(memarg) => (memarg, 4),
+        V128Load8x8(memarg, _) | V128Load16x4(memarg, _) | V128Load32x2(memarg, _) => (memarg, 3),
+        V128Load8Splat(memarg) => (memarg, 0),
+        V128Load16Splat(memarg) => (memarg, 1),
+        V128Load32Splat(memarg) | V128Load32Zero(memarg) => (memarg, 2),
+        V128Load64Splat(memarg) | V128Load64Zero(memarg) => (memarg, 3),
+        V128Load8Lane(memarg, _) | V128Store8Lane(memarg, _) => (memarg, 0),
+        V128Load16Lane(memarg, _) | V128Store16Lane(memarg, _) => (memarg, 1),
+        V128Load32Lane(memarg, _) | V128Store32Lane(memarg, _) => (memarg, 2),
+        V128Load64Lane(memarg, _) | V128Store64Lane(memarg, _) => (memarg, 3),
+        _ => return None,
+    })
+}
+
+/// The operands and results of a vector instruction.
+fn vector_signature(instruction: &VectorInstruction) -> Signature {
+    use VectorInstruction::*;
+    match instruction {
+        V128Load(_) | V128Load8x8(..) | V128Load16x4(..) | V128Load32x2(..) | V128Load8Splat(_)
+        | V128Load16Splat(_) | V128Load32Splat(_) | V128Load64Splat(_) | V128Load32Zero(_)
+        | V128Load64Zero(_) => (&[I32], &[V128]),
+        V128Store(_) | V128Store8Lane(..) | V128Store16Lane(..) | V128Store32Lane(..)
+        | V128Store64Lane(..) => (&[I32, V128], &[]),
+        V128Load8Lane(..) | V128Load16Lane(..) | V128Load32Lane(..) | V128Load64Lane(..) => {
+            (&[I32, V128], &[V128])
+        }
+        V128Const(_) => (&[], &[V128]),
+
+        I8x16ExtractLane(..) | I16x8ExtractLane(..) | I32x4ExtractLane(_) => (&[V128], &[I32]),
+        I64x2ExtractLane(_) => (&[V128], &[I64]),
+        F32x4ExtractLane(_) => (&[V128], &[F32]),
+        F64x2ExtractLane(_) => (&[V128], &[F64]),
+        I8x16ReplaceLane(_) | I16x8ReplaceLane(_) | I32x4ReplaceLane(_) => (&[V128, I32], &[V128]),
+        I64x2ReplaceLane(_) => (&[V128, I64], &[V128]),
+        F32x4ReplaceLane(_) => (&[V128, F32], &[V128]),
+        F64x2ReplaceLane(_) => (&[V128, F64], &[V128]),
+        I8x16Splat | I16x8Splat | I32x4Splat => (&[I32], &[V128]),
+        I64x2Splat => (&[I64], &[V128]),
+        F32x4Splat => (&[F32], &[V128]),
+        F64x2Splat => (&[F64], &[V128]),
+
+        V128AnyTrue | I8x16AllTrue | I8x16Bitmask | I16x8AllTrue | I16x8Bitmask | I32x4AllTrue
+        | I32x4Bitmask | I64x2AllTrue | I64x2Bitmask => (&[V128], &[I32]),
+        I8x16ShiftLeft | I8x16ShiftRight(_) | I16x8ShiftLeft | I16x8ShiftRight(_)
+        | I32x4ShiftLeft | I32x4ShiftRight(_) | I64x2ShiftLeft | I64x2ShiftRight(_) => {
+            (&[V128, I32], &[V128])
+        }
+        V128BitSelect => (&[V128, V128, V128], &[V128]),
+
+        V128Not
+        | I8x16Abs
+        | I8x16Neg
+        | I8x16PopCnt
+        | I16x8ExtAddPairWiseI8x16(_)
+        | I16x8Abs
+        | I16x8Neg
+        | I16x8ExtendLowI8x16(_)
+        | I16x8ExtendHighI8x16(_)
+        | I32x4ExtAddPairwiseI16x8(_)
+        | I32x4Abs
+        | I32x4Neg
+        | I32x4ExtendLowI16x8(_)
+        | I32x4ExtendHighI16x8(_)
+        | I64x2Abs
+        | I64x2Neg
+        | I64x2ExtendLowI32x4(_)
+        | I64x2ExtendHighI32x4(_)
+        | F32x4Ceil
+        | F32x4Floor
+        | F32x4Trunc
+        | F32x4Nearest
+        | F32x4Abs
+        | F32x4Neg
+        | F32x4Sqrt
+        | F64x2Ceil
+        | F64x2Floor
+        | F64x2Trunc
+        | F64x2Nearest
+        | F64x2Abs
+        | F64x2Neg
+        | F64x2Sqrt
+        | I32x4TruncSatF32x4(_)
+        | F32x4ConvertI32x4(_)
+        | I32x4TruncSatF64x2Zero(_)
+        | F64x2ConvertLowI32x4(_)
+        | F32x4DemoteF64x2Zero
+        | F64x2PromoteLowF32x4 => (&[V128], &[V128]),
+
+        I8x16Shuffle(_)
+        | I8x16Swizzle
+        | I8x16Equal
+        | I8x16NotEqual
+        | I8x16LessThan(_)
+        | I8x16GreaterThan(_)
+        | I8x16LessOrEqualTo(_)
+        | I8x16GreaterOrEqualTo(_)
+        | I16x8Equal
+        | I16x8NotEqual
+        | I16x8LessThan(_)
+        | I16x8GreaterThan(_)
+        | I16x8LessOrEqualTo(_)
+        | I16x8GreaterOrEqualTo(_)
+        | I32x4Equal
+        | I32x4NotEqual
+        | I32x4LessThan(_)
+        | I32x4GreaterThan(_)
+        | I32x4LessOrEqualTo(_)
+        | I32x4GreaterOrEqualTo(_)
+        | I64x2Equal
+        | I64x2NotEqual
+        | I64x2LessThan
+        | I64x2GreaterThan
+        | I64x2LessOrEqualTo
+        | I64x2GreaterOrEqualTo
+        | F32x4Equal
+        | F32x4NotEqual
+        | F32x4LessThan
+        | F32x4GreaterThan
+        | F32x4LessOrEqualTo
+        | F32x4GreaterOrEqualTo
+        | F64x2Equal
+        | F64x2NotEqual
+        | F64x2LessThan
+        | F64x2GreaterThan
+        | F64x2LessOrEqualTo
+        | F64x2GreaterOrEqualTo
+        | V128And
+        | V128AndNot
+        | V128Or
+        | V128Xor
+        | I8x16NarrowI16x8(_)
+        | I8x16Add
+        | I8x16AddSaturating(_)
+        | I8x16Sub
+        | I8x16SubSaturating(_)
+        | I8x16Min(_)
+        | I8x16Max(_)
+        | I8x16Average
+        | I16x8Q16MulrSat
+        | I16x8NarrowI32x4(_)
+        | I16x8Add
+        | I16x8AddSaturating(_)
+        | I16x8Sub
+        | I16x8SubSaturating(_)
+        | I16x8Mul
+        | I16x8Min(_)
+        | I16x8Max(_)
+        | I16x8Average
+        | I16x8ExtMulLowI8x16(_)
+        | I16x8ExtMulHighI8x16(_)
+        | I32x4Add
+        | I32x4Sub
+        | I32x4Mul
+        | I32x4Min(_)
+        | I32x4Max(_)
+        | I32x4DotI16x8
+        | I32x4ExtMulLowI16x8(_)
+        | I32x4ExtMulHighI16x8(_)
+        | I64x2Add
+        | I64x2Sub
+        | I64x2Mul
+        | I64x2ExtMulLowI32x4(_)
+        | I64x2ExtMulHighI32x4(_)
+        | F32x4Add
+        | F32x4Sub
+        | F32x4Mul
+        | F32x4Div
+        | F32x4Min
+        | F32x4Max
+        | F32x4PMin
+        | F32x4PMax
+        | F64x2Add
+        | F64x2Sub
+        | F64x2Mul
+        | F64x2Div
+        | F64x2Min
+        | F64x2Max
+        | F64x2PMin
+        | F64x2PMax => (&[V128, V128], &[V128]),
+    }
+}
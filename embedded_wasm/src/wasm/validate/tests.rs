@@ -0,0 +1,323 @@
+#[cfg(feature = "lazy")]
+use crate::instruction::FuncIdx;
+use crate::{test_utils::encode, ErrorKind, ValidationError, ValidationErrorKind, Wasm};
+
+/// The reason that the given module is invalid. With the `lazy` feature the function bodies are validated when they are decoded, so every body is decoded.
+fn invalid_with_locals_limit(bytes: &[u8], limit: usize) -> ValidationError {
+    let kind = match Wasm::parse_with_locals_limit(bytes, limit) {
+        Err(error) => error.kind,
+        #[cfg(feature = "lazy")]
+        Ok(wasm) => {
+            (0..wasm.functions.len())
+                .find_map(|index| {
+                    let function = FuncIdx((wasm.imported_function_count + index) as u32);
+                    wasm.decode_code(function).unwrap().err()
+                })
+                .expect("The module is valid")
+                .kind
+        }
+        #[cfg(not(feature = "lazy"))]
+        Ok(_) => panic!("The module is valid"),
+    };
+    match kind {
+        ErrorKind::Invalid(error) => error,
+        kind => panic!("Expected a validation error, got {:?}", kind),
+    }
+}
+
+fn invalid(wat: &str) -> ValidationErrorKind {
+    invalid_with_locals_limit(&encode(wat), 256).kind
+}
+
+fn assert_valid(wat: &str) {
+    let bytes = encode(wat);
+    let wasm = Wasm::parse(&bytes).unwrap();
+    #[cfg(feature = "lazy")]
+    for index in 0..wasm.functions.len() {
+        let function = FuncIdx((wasm.imported_function_count + index) as u32);
+        wasm.decode_code(function).unwrap().unwrap();
+    }
+    #[cfg(not(feature = "lazy"))]
+    let _ = wasm;
+}
+
+#[test]
+fn type_mismatch() {
+    assert_eq!(
+        invalid("(module (func (result i32) (i64.const 0)))"),
+        ValidationErrorKind::TypeMismatch
+    );
+    assert_eq!(
+        invalid("(module (func (drop (i32.add (i32.const 0) (f32.const 0)))))"),
+        ValidationErrorKind::TypeMismatch
+    );
+    // Values are left on the stack at the end of the function
+    assert_eq!(
+        invalid("(module (func (i32.const 0)))"),
+        ValidationErrorKind::TypeMismatch
+    );
+}
+
+#[test]
+fn type_mismatch_position() {
+    let error = invalid_with_locals_limit(
+        &encode(
+            r#"(module
+                (func)
+                (func (local i32)
+                    (i32.const 1)
+                    (f32.const 2)
+                    (i32.add)
+                    (drop)))"#,
+        ),
+        256,
+    );
+    assert_eq!(error.kind, ValidationErrorKind::TypeMismatch);
+    assert_eq!(error.function, Some(1));
+    // `i32.add` follows `i32.const 1` and `f32.const 2`, which are two and five bytes
+    assert_eq!(error.position, 7);
+}
+
+#[test]
+fn stack_underflow() {
+    assert_eq!(
+        invalid("(module (func (drop)))"),
+        ValidationErrorKind::TypeMismatch
+    );
+    // A block can not pop the values of the enclosing block
+    assert_eq!(
+        invalid("(module (func (i32.const 1) (block (drop)) (drop)))"),
+        ValidationErrorKind::TypeMismatch
+    );
+}
+
+#[test]
+fn stack_underflow_in_unreachable_code() {
+    // The stack is polymorphic after `unreachable`, so any value can be popped
+    assert_valid("(module (func (result i32) (unreachable) (i32.add)))");
+    assert_valid("(module (func (block (br 0) (drop) (f64.const 0) (drop))))");
+    // But the values that are pushed after it must still have the right type
+    assert_eq!(
+        invalid("(module (func (result i32) (unreachable) (i64.const 0)))"),
+        ValidationErrorKind::TypeMismatch
+    );
+    // A block in unreachable code has a stack of its own, which is not polymorphic
+    assert_eq!(
+        invalid("(module (func (unreachable) (block (drop))))"),
+        ValidationErrorKind::TypeMismatch
+    );
+}
+
+#[test]
+fn if_without_else() {
+    // Without an `else`, the parameters of the `if` are its results when the condition is false
+    assert_valid(
+        "(module (func (param i32) (result i32)
+            (i32.const 1)
+            (if (param i32) (result i32) (local.get 0) (then (i32.const 2) (i32.add)))))",
+    );
+    assert_eq!(
+        invalid(
+            "(module (func (param i32) (result i32)
+                (if (result i32) (local.get 0) (then (i32.const 1)))))"
+        ),
+        ValidationErrorKind::TypeMismatch
+    );
+    assert_eq!(
+        invalid(
+            "(module (func (param i32) (result i64)
+                (i32.const 1)
+                (if (param i32) (result i64) (local.get 0) (then (drop) (i64.const 2)))))"
+        ),
+        ValidationErrorKind::TypeMismatch
+    );
+}
+
+#[test]
+fn unknown_label() {
+    assert_eq!(
+        invalid("(module (func (block (br 2))))"),
+        ValidationErrorKind::UnknownLabel
+    );
+    assert_eq!(
+        invalid("(module (func (br_table 0 1 (i32.const 0))))"),
+        ValidationErrorKind::UnknownLabel
+    );
+}
+
+#[test]
+fn unknown_local() {
+    assert_eq!(
+        invalid("(module (func (param i32) (local i64) (drop (local.get 2))))"),
+        ValidationErrorKind::UnknownLocal
+    );
+}
+
+#[test]
+fn unknown_global() {
+    assert_eq!(
+        invalid("(module (func (drop (global.get 0))))"),
+        ValidationErrorKind::UnknownGlobal
+    );
+    // The initializer of a global can only use imported globals
+    assert_eq!(
+        invalid("(module (global i32 (i32.const 0)) (global i32 (global.get 0)))"),
+        ValidationErrorKind::UnknownGlobal
+    );
+}
+
+#[test]
+fn unknown_function() {
+    assert_eq!(
+        invalid("(module (func (call 1)))"),
+        ValidationErrorKind::UnknownFunction
+    );
+}
+
+#[test]
+fn unknown_type() {
+    assert_eq!(
+        invalid("(module (func (block (type 3))))"),
+        ValidationErrorKind::UnknownType
+    );
+}
+
+#[test]
+fn unknown_table() {
+    assert_eq!(
+        invalid("(module (func (drop (table.size 0))))"),
+        ValidationErrorKind::UnknownTable
+    );
+}
+
+#[test]
+fn unknown_memory() {
+    assert_eq!(
+        invalid("(module (func (drop (memory.size))))"),
+        ValidationErrorKind::UnknownMemory
+    );
+    assert_eq!(
+        invalid(r#"(module (data (i32.const 0) "a"))"#),
+        ValidationErrorKind::UnknownMemory
+    );
+}
+
+#[test]
+fn unknown_data() {
+    assert_eq!(
+        invalid("(module (memory 1) (func (data.drop 0)))"),
+        ValidationErrorKind::UnknownData
+    );
+}
+
+#[test]
+fn unknown_element() {
+    assert_eq!(
+        invalid("(module (func (elem.drop 0)))"),
+        ValidationErrorKind::UnknownElement
+    );
+}
+
+#[test]
+fn unknown_export() {
+    assert_eq!(
+        invalid(r#"(module (func) (export "f" (func 1)))"#),
+        ValidationErrorKind::UnknownExport
+    );
+}
+
+#[test]
+fn immutable_global() {
+    assert_eq!(
+        invalid("(module (global i32 (i32.const 0)) (func (global.set 0 (i32.const 1))))"),
+        ValidationErrorKind::ImmutableGlobal
+    );
+}
+
+#[test]
+fn invalid_alignment() {
+    assert_eq!(
+        invalid("(module (memory 1) (func (drop (i32.load align=8 (i32.const 0)))))"),
+        ValidationErrorKind::InvalidAlignment
+    );
+}
+
+#[test]
+fn undeclared_function_reference() {
+    assert_eq!(
+        invalid("(module (func $f) (func (drop (ref.func $f))))"),
+        ValidationErrorKind::UndeclaredFunctionReference
+    );
+}
+
+#[test]
+fn invalid_constant_expression() {
+    assert_eq!(
+        invalid(r#"(module (import "env" "g" (global (mut i32))) (global i32 (global.get 0)))"#),
+        ValidationErrorKind::InvalidConstantExpression
+    );
+}
+
+#[test]
+fn duplicate_export_name() {
+    assert_eq!(
+        invalid(r#"(module (func) (export "f" (func 0)) (export "f" (func 0)))"#),
+        ValidationErrorKind::DuplicateExportName
+    );
+}
+
+#[test]
+fn invalid_start_function() {
+    assert_eq!(
+        invalid("(module (func (param i32)) (start 0))"),
+        ValidationErrorKind::InvalidStartFunction
+    );
+}
+
+#[test]
+fn invalid_limits() {
+    assert_eq!(
+        invalid("(module (memory 2 1))"),
+        ValidationErrorKind::InvalidLimits
+    );
+    assert_eq!(
+        invalid("(module (memory 65537))"),
+        ValidationErrorKind::InvalidLimits
+    );
+}
+
+#[test]
+fn multiple_memories() {
+    assert_eq!(
+        invalid("(module (memory 1) (memory 1))"),
+        ValidationErrorKind::MultipleMemories
+    );
+}
+
+#[test]
+fn function_count_mismatch() {
+    #[rustfmt::skip]
+    let bytes = [
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+        // A type section with the type `[] -> []`
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+        // A function section with a single function, but no code section
+        0x03, 0x02, 0x01, 0x00,
+    ];
+    assert_eq!(
+        invalid_with_locals_limit(&bytes, 256).kind,
+        ValidationErrorKind::FunctionCountMismatch
+    );
+}
+
+#[test]
+fn too_many_locals() {
+    let bytes = encode("(module (func (param i32) (local i32 i64)))");
+    assert!(Wasm::parse_with_locals_limit(&bytes, 3).is_ok());
+    let error = invalid_with_locals_limit(&bytes, 2);
+    assert_eq!(error.kind, ValidationErrorKind::TooManyLocals);
+    assert_eq!(error.function, Some(0));
+    // `parse` allows 256 locals
+    let wat = ["(module (func (local", &" i32".repeat(257), ")))"].concat();
+    assert_eq!(invalid(&wat), ValidationErrorKind::TooManyLocals);
+}
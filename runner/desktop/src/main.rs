@@ -40,6 +40,8 @@ struct State;
 
 embedded_wasm::derive_ffi_handler! {
     impl State {
+        // The handle is a raw `i32`, because this macro can not pass an `ExternRef`: Rust has no type for `externref` in the FFI of the guest.
+        // Implement `FfiHandler` manually to pass opaque handles as `externref`s.
         pub fn get_led_handle(&self, led: i32) -> i32 {
             led
         }